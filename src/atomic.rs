@@ -0,0 +1,46 @@
+//! Writing files so a run that is killed halfway never leaves a
+//! manifest cut short: the new contents go to a temporary file next to
+//! it, which is then renamed over it in one step
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Replace the contents of a file, all at once
+///
+/// The permissions of the file are kept, and a symbolic link is
+/// followed so the file it points to is replaced rather than the link
+///
+/// * `path` - the file to write
+/// * `contents` - what it should contain
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let temp = temp_path(&path);
+    let written =
+        write_temp(&path, &temp, contents.as_ref()).and_then(|()| fs::rename(&temp, &path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Write the temporary file and make sure it is on disk, with the
+/// permissions of the file it replaces
+fn write_temp(path: &Path, temp: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = fs::File::create(temp)?;
+    file.write_all(contents)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.sync_all()
+}
+
+/// A name for the temporary file in the same directory, since a rename
+/// is only atomic within one file system
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map_or_else(|| "file".into(), |n| n.to_string_lossy());
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
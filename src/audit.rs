@@ -0,0 +1,168 @@
+//! The `audit` subcommand: compare what each member has published to
+//! crates.io with the release tags in the repository
+use std::collections::BTreeSet;
+
+use anyhow::Error;
+
+use crate::{
+    git,
+    json::Json,
+    registry,
+    report::DifferencesFound,
+    version::{self, Version},
+    workspace::Workspace,
+    Format,
+};
+
+/// What we found for one member
+#[derive(Debug)]
+enum Audit {
+    /// versions published without a tag, and tags never published
+    Checked {
+        /// published to crates.io, but with no tag
+        untagged: Vec<Version>,
+        /// tagged, but never published to crates.io
+        unpublished: Vec<Version>,
+    },
+    /// nothing has been published yet
+    Never,
+    /// `publish = false`, so there is nothing to compare
+    Private,
+    /// we couldn't ask crates.io
+    Unknown(String),
+}
+
+/// Cross reference the published versions of each member with the tags
+///
+/// A tag counts for every member if it is a release tag for the whole
+/// workspace (see `tag-prefix`), or for one member if it is that member's
+/// name followed by the version, like `mycrate-v1.2.3` or `mycrate@1.2.3`.
+/// Workspace tags from before a member was first published are ignored,
+/// since the member probably didn't exist yet
+///
+/// * `ws` - the workspace to audit
+/// * `stable` - leave out pre-release versions, both published and tagged
+/// * `format` - text for people, or JSON for tooling
+/// * `quiet` - don't print the text report
+///
+/// Fails if any member has versions and tags that don't agree
+pub fn run(ws: &Workspace, stable: bool, format: Format, quiet: bool) -> Result<(), Error> {
+    let tags = git::tags();
+    let audits = ws
+        .members
+        .iter()
+        .filter_map(|m| {
+            let name = m.name()?;
+            if !m.publishable() {
+                return Some((name, Audit::Private));
+            }
+            let published = match registry::versions(name) {
+                Ok(versions) => versions
+                    .iter()
+                    .filter_map(|v| v.parse::<Version>().ok())
+                    .filter(|v| !stable || v.pre.is_empty())
+                    .collect::<BTreeSet<_>>(),
+                Err(e) => return Some((name, Audit::Unknown(e.to_string()))),
+            };
+            let Some(first) = published.first() else {
+                return Some((name, Audit::Never));
+            };
+            let tagged = tags
+                .iter()
+                .filter_map(|tag| {
+                    if let Some(v) = ws.config.tag_version(tag).and_then(|v| v.parse().ok()) {
+                        return (v >= *first).then_some(v);
+                    }
+                    let (prefix, v) = version::split_tag(tag)?;
+                    let prefix = prefix.strip_suffix('v').unwrap_or(prefix);
+                    (prefix.strip_suffix(['-', '_', '@', '/'])? == name).then_some(v)
+                })
+                .filter(|v| !stable || v.pre.is_empty())
+                .collect::<BTreeSet<_>>();
+            Some((
+                name,
+                Audit::Checked {
+                    untagged: published.difference(&tagged).cloned().collect(),
+                    unpublished: tagged.difference(&published).cloned().collect(),
+                },
+            ))
+        })
+        .collect::<Vec<_>>();
+    let disagreeing = audits
+        .iter()
+        .filter(|(_, a)| {
+            matches!(a, Audit::Checked { untagged, unpublished }
+                if !untagged.is_empty() || !unpublished.is_empty())
+        })
+        .count();
+
+    let list = |versions: &[Version]| versions.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    match format {
+        Format::Text if !quiet => {
+            for (name, audit) in &audits {
+                match audit {
+                    Audit::Checked {
+                        untagged,
+                        unpublished,
+                    } if untagged.is_empty() && unpublished.is_empty() => println!("{name}: ok"),
+                    Audit::Checked {
+                        untagged,
+                        unpublished,
+                    } => {
+                        println!("{name}:");
+                        if !untagged.is_empty() {
+                            println!("  published without a tag: {}", list(untagged).join(", "));
+                        }
+                        if !unpublished.is_empty() {
+                            println!(
+                                "  tagged but never published: {}",
+                                list(unpublished).join(", ")
+                            );
+                        }
+                    }
+                    Audit::Never => println!("{name}: never published"),
+                    Audit::Private => println!("{name}: not publishable"),
+                    Audit::Unknown(e) => println!("{name}: unknown ({e})"),
+                }
+            }
+        }
+        Format::Text => {}
+        Format::Json => {
+            let crates = audits
+                .iter()
+                .map(|(name, audit)| {
+                    let (state, untagged, unpublished, error) = match audit {
+                        Audit::Checked {
+                            untagged,
+                            unpublished,
+                        } => {
+                            let state = if untagged.is_empty() && unpublished.is_empty() {
+                                "ok"
+                            } else {
+                                "mismatch"
+                            };
+                            (state, list(untagged), list(unpublished), None)
+                        }
+                        Audit::Never => ("never-published", vec![], vec![], None),
+                        Audit::Private => ("private", vec![], vec![], None),
+                        Audit::Unknown(e) => ("unknown", vec![], vec![], Some(e.as_str())),
+                    };
+                    Json::object()
+                        .with("name", *name)
+                        .with("state", state)
+                        .with("published_without_tag", untagged)
+                        .with("tagged_not_published", unpublished)
+                        .with("error", error)
+                })
+                .collect::<Vec<_>>();
+            println!("{:#}", Json::from(crates));
+        }
+    }
+    if disagreeing > 0 {
+        return Err(DifferencesFound(format!(
+            "{disagreeing} crate(s) have published versions and tags that don't agree"
+        ))
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Calendar versions, like `2024.6.1`: the year, the month, and a
+//! counter for releases within the month
+//!
+//! Cargo needs versions to be semantic versions, which can't have
+//! leading zeros, so June is `6` and not `06`
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::version::Version;
+
+/// Check that a version is a calendar version
+///
+/// Pre-releases and build metadata are allowed, as for any version
+///
+/// * `version` - the version about to be applied
+pub fn check(version: &str) -> Result<(), Error> {
+    let parsed = version
+        .parse::<Version>()
+        .context(format!("{version} isn't a calendar version like 2024.6.0"))?;
+    if parsed.major < 1000 || parsed.major > 9999 {
+        bail!(
+            "{version} isn't a calendar version: {} isn't a four digit year",
+            parsed.major
+        )
+    }
+    if !(1..=12).contains(&parsed.minor) {
+        bail!(
+            "{version} isn't a calendar version: {} isn't a month",
+            parsed.minor
+        )
+    }
+    Ok(())
+}
+
+/// The next calendar version after the current one
+///
+/// This is the first release of this month, unless the current
+/// version is already from this month, in which case its counter goes
+/// up by one. A pre-release of this month becomes its release
+///
+/// * `current` - the current workspace version, if there is one
+pub fn next(current: Option<&str>) -> Result<String, Error> {
+    let (year, month) = today()?;
+    let current = current
+        .map(str::parse::<Version>)
+        .transpose()
+        .context("Can't compute the next calendar version")?
+        .filter(|v| (v.major, v.minor) == (year, month));
+    let micro = match current {
+        Some(v) if !v.pre.is_empty() => v.patch,
+        Some(v) => v.patch + 1,
+        None => 0,
+    };
+    Ok(format!("{year}.{month}.{micro}"))
+}
+
+/// The year and month today, in UTC
+///
+/// `SOURCE_DATE_EPOCH` is used instead of the clock if it is set, so
+/// reproducible builds get the same version
+fn today() -> Result<(u64, u64), Error> {
+    let seconds = match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(epoch) => epoch
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| anyhow!("SOURCE_DATE_EPOCH isn't a number of seconds: {epoch}"))?,
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("The clock is before 1970")?
+            .as_secs(),
+    };
+    // days since 1970-01-01 to a civil date, from Howard Hinnant's
+    // "chrono-Compatible Low-Level Date Algorithms"
+    let days = seconds / 86400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    Ok((year, month))
+}
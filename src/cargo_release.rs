@@ -0,0 +1,120 @@
+//! Settings taken from cargo-release, so workspaces moving over from it
+//! don't need to repeat them
+//!
+//! cargo-release reads `[workspace.metadata.release]` and then
+//! `release.toml` at the top level, then `[package.metadata.release]`
+//! and `release.toml` in each crate, with later ones taking precedence.
+//! Only the settings that mean something here are read, and they are
+//! only used where our own table doesn't say otherwise
+use anyhow::Error;
+use toml_edit::{Document, Item};
+
+/// The cargo-release settings that apply to the workspace or a crate
+#[derive(Debug, Default, Clone)]
+pub struct Settings {
+    /// `tag-prefix`, which takes the place of `{{prefix}}` in `tag-name`
+    pub tag_prefix: Option<String>,
+    /// `tag-name`, the template for release tag names
+    pub tag_name: Option<String>,
+    /// `pre-release-hook`, run once the versions have been changed
+    pub hook: Option<Vec<String>>,
+    /// `release`, false for crates cargo-release leaves alone
+    pub release: Option<bool>,
+    /// `publish`, false for crates that aren't published
+    pub publish: Option<bool>,
+}
+
+impl Settings {
+    /// Read the top level settings
+    ///
+    /// * `doc` - the parsed top level Cargo.toml
+    /// * `file` - the contents of the top level `release.toml`, if any
+    pub fn workspace(doc: &Document, file: Option<&str>) -> Result<Settings, Error> {
+        let mut settings = Settings::default();
+        if let Some(table) = doc
+            .get("workspace")
+            .and_then(|w| w.get("metadata"))
+            .and_then(|m| m.get("release"))
+        {
+            settings.merge(table);
+        }
+        settings.merge_file(file)?;
+        Ok(settings)
+    }
+
+    /// Read the settings for one crate, on top of the top level ones
+    ///
+    /// * `doc` - the parsed Cargo.toml of the crate
+    /// * `file` - the contents of the crate's `release.toml`, if any
+    pub fn package(&self, doc: &Document, file: Option<&str>) -> Result<Settings, Error> {
+        let mut settings = self.clone();
+        if let Some(table) = doc
+            .get("package")
+            .and_then(|p| p.get("metadata"))
+            .and_then(|m| m.get("release"))
+        {
+            settings.merge(table);
+        }
+        settings.merge_file(file)?;
+        Ok(settings)
+    }
+
+    /// What release tags have before the version, if `tag-prefix` or
+    /// `tag-name` was set
+    ///
+    /// This is `None` if the tag name doesn't end with the version, or
+    /// has anything specific to one crate in it, like `{{crate_name}}`,
+    /// since tags like that aren't for the whole workspace
+    pub fn release_tag_prefix(&self) -> Option<String> {
+        if self.tag_prefix.is_none() && self.tag_name.is_none() {
+            return None;
+        }
+        let name = self.tag_name.as_deref().unwrap_or("{{prefix}}v{{version}}");
+        let prefix = name
+            .strip_suffix("{{version}}")?
+            .replace("{{prefix}}", self.tag_prefix.as_deref().unwrap_or_default());
+        (!prefix.contains("{{")).then_some(prefix)
+    }
+
+    /// Read the settings from a cargo-release table, replacing any
+    /// already read
+    ///
+    /// Values of the wrong type are left for cargo-release to complain
+    /// about, and are ignored here
+    fn merge(&mut self, table: &Item) {
+        if let Some(prefix) = table.get("tag-prefix").and_then(Item::as_str) {
+            self.tag_prefix = Some(prefix.to_string());
+        }
+        if let Some(name) = table.get("tag-name").and_then(Item::as_str) {
+            self.tag_name = Some(name.to_string());
+        }
+        if let Some(hook) = table.get("pre-release-hook") {
+            // a string is split on spaces, as cargo-release does
+            if let Some(line) = hook.as_str() {
+                self.hook = Some(line.split_whitespace().map(String::from).collect());
+            } else if let Some(args) = hook.as_array() {
+                self.hook = Some(
+                    args.iter()
+                        .filter_map(|a| a.as_str())
+                        .map(String::from)
+                        .collect(),
+                );
+            }
+        }
+        if let Some(release) = table.get("release").and_then(Item::as_bool) {
+            self.release = Some(release);
+        }
+        if let Some(publish) = table.get("publish").and_then(Item::as_bool) {
+            self.publish = Some(publish);
+        }
+    }
+
+    /// Read the settings from a `release.toml`, where they are at the
+    /// top level
+    fn merge_file(&mut self, file: Option<&str>) -> Result<(), Error> {
+        if let Some(contents) = file {
+            self.merge(contents.parse::<Document>()?.as_item());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! Colors for what we print to a terminal, so the differences stand
+//! out in a large workspace
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+
+/// When to color the output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum When {
+    /// When printing to a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// Always, even when the output goes to a file or pipe
+    Always,
+    /// Never
+    Never,
+}
+
+impl When {
+    /// Check if colors should be used
+    ///
+    /// `NO_COLOR` is honored as <https://no-color.org> asks: set to
+    /// anything but the empty string, it turns colors off
+    pub fn enabled(self) -> bool {
+        match self {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::env::var_os("TERM").is_none_or(|t| t != "dumb")
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// What a line of output is about
+#[derive(Debug, Clone, Copy)]
+pub enum Paint {
+    /// something that doesn't have the version, in red
+    Wrong,
+    /// something that was fixed, in green
+    Fixed,
+    /// the reasoning behind a decision, or anything else that isn't
+    /// news, dimmed
+    Quiet,
+    /// a heading, like the line numbers of a diff hunk, in cyan
+    Heading,
+}
+
+impl Paint {
+    /// The text with the color around it, if colors are on
+    ///
+    /// * `text` - what to print
+    /// * `on` - whether colors are used at all
+    pub fn apply(self, text: &str, on: bool) -> String {
+        if !on {
+            return text.to_string();
+        }
+        let code = match self {
+            Paint::Wrong => "31",
+            Paint::Fixed => "32",
+            Paint::Quiet => "2",
+            Paint::Heading => "36",
+        };
+        format!("\x1b[{code}m{text}\x1b[0m")
+    }
+}
+
+/// A unified diff with the removed lines in red and the added ones in
+/// green
+///
+/// * `diff` - the diff, as printed without colors
+/// * `on` - whether colors are used at all
+pub fn diff(diff: &str, on: bool) -> String {
+    diff.lines()
+        .map(|line| {
+            let paint = if line.starts_with("---") || line.starts_with("+++") {
+                None
+            } else if line.starts_with("@@") {
+                Some(Paint::Heading)
+            } else if line.starts_with('-') {
+                Some(Paint::Wrong)
+            } else if line.starts_with('+') {
+                Some(Paint::Fixed)
+            } else {
+                None
+            };
+            let line = paint.map_or(line.to_string(), |p| p.apply(line, on));
+            line + "\n"
+        })
+        .collect()
+}
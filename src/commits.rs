@@ -0,0 +1,70 @@
+//! Working out the next release from the commits since the last one,
+//! written as Conventional Commits (`feat: ...`, `fix(parser): ...`)
+use anyhow::{bail, Error};
+
+use crate::{config::Config, git, version::Level};
+
+/// What one commit asks of the next release
+///
+/// Commits that aren't features, fixes or breaking changes, like
+/// `docs:` or `chore:`, or that don't follow the convention at all, ask
+/// for nothing
+///
+/// * `message` - the whole commit message
+fn classify(message: &str) -> Option<Level> {
+    let subject = message.lines().next()?;
+    let (head, _) = subject.split_once(':')?;
+    let breaking = head.ends_with('!')
+        || message.lines().any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+    let kind = head
+        .trim_end_matches('!')
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if breaking {
+        Some(Level::Major)
+    } else if kind == "feat" {
+        Some(Level::Minor)
+    } else if kind == "fix" {
+        Some(Level::Patch)
+    } else {
+        None
+    }
+}
+
+/// The level to bump to release the commits since the newest release
+/// tag reachable from HEAD, or every commit if nothing was released
+///
+/// A breaking change makes it a major release, a feature a minor one,
+/// and a fix a patch one
+///
+/// * `config` - the settings, for the tag prefix
+pub fn level(config: &Config) -> Result<Level, Error> {
+    let tag = git::latest_release_tag(config, false).map(|(tag, _)| tag);
+    let range = match &tag {
+        Some(tag) => format!("{tag}..HEAD"),
+        None => "HEAD".to_string(),
+    };
+    let log = git::git(&["log", "--format=%B%x1e", &range])?;
+    let level = log
+        .split('\x1e')
+        .filter_map(|message| classify(message.trim()))
+        .max_by_key(|level| match level {
+            Level::Major => 2,
+            Level::Minor => 1,
+            Level::Patch => 0,
+        });
+    match (level, tag) {
+        (Some(level), _) => Ok(level),
+        (None, Some(tag)) => bail!(
+            "No features, fixes or breaking changes since {tag}, so there is nothing to release"
+        ),
+        (None, None) => {
+            bail!("No features, fixes or breaking changes, so there is nothing to release")
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! The `compare` subcommand: the version of each crate in two copies of
+//! the workspace, such as two release tags
+use std::{ffi::OsString, path::Path};
+
+use anyhow::{anyhow, Error};
+
+use crate::{
+    git,
+    json::Json,
+    version::Version,
+    workspace::{self, Workspace},
+    Format,
+};
+
+/// How the version of a crate changed from one side to the other
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Delta {
+    /// only in the new workspace
+    Added,
+    /// only in the old workspace
+    Removed,
+    /// the same version on both sides
+    Unchanged,
+    /// the major version went up
+    Major,
+    /// the minor version went up
+    Minor,
+    /// the patch version went up
+    Patch,
+    /// only the pre-release or build metadata changed
+    Prerelease,
+    /// the version went down
+    Downgrade,
+    /// the version changed, but one side isn't a semantic version
+    Changed,
+}
+
+impl Delta {
+    /// Work out the change from one version to another
+    fn between(old: Option<&str>, new: Option<&str>) -> Delta {
+        let (old, new) = match (old, new) {
+            (None, _) => return Delta::Added,
+            (_, None) => return Delta::Removed,
+            (Some(old), Some(new)) if old == new => return Delta::Unchanged,
+            (Some(old), Some(new)) => (old, new),
+        };
+        let (Ok(old), Ok(new)) = (old.parse::<Version>(), new.parse::<Version>()) else {
+            return Delta::Changed;
+        };
+        if new < old {
+            Delta::Downgrade
+        } else if new.major != old.major {
+            Delta::Major
+        } else if new.minor != old.minor {
+            Delta::Minor
+        } else if new.patch != old.patch {
+            Delta::Patch
+        } else {
+            Delta::Prerelease
+        }
+    }
+
+    /// The name for this change in the output
+    fn name(self) -> &'static str {
+        match self {
+            Delta::Added => "added",
+            Delta::Removed => "removed",
+            Delta::Unchanged => "unchanged",
+            Delta::Major => "major",
+            Delta::Minor => "minor",
+            Delta::Patch => "patch",
+            Delta::Prerelease => "pre-release",
+            Delta::Downgrade => "downgrade",
+            Delta::Changed => "changed",
+        }
+    }
+}
+
+/// Report how the version of each crate changed between two copies of
+/// the workspace
+///
+/// * `old` - a directory, or a git ref, with the earlier workspace
+/// * `new` - a directory, or a git ref, with the later workspace
+/// * `changed` - leave out crates whose version is the same on both sides
+/// * `format` - text for people, or JSON for tooling
+pub fn run(old: &str, new: &str, changed: bool, format: Format) -> Result<(), Error> {
+    let before = load(old)?;
+    let after = load(new)?;
+    let versions = |ws: &Workspace| {
+        ws.members
+            .iter()
+            .filter_map(|m| {
+                Some((
+                    m.name()?.to_string(),
+                    ws.member_version(m).map(String::from),
+                ))
+            })
+            .collect::<Vec<_>>()
+    };
+    let before = versions(&before);
+    let after = versions(&after);
+    let find = |side: &[(String, Option<String>)], name: &str| {
+        side.iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.clone())
+    };
+
+    // crates in the order of the new workspace, then any that were removed
+    let mut rows = after
+        .iter()
+        .map(|(name, version)| (name.as_str(), find(&before, name), version.clone()))
+        .collect::<Vec<_>>();
+    rows.extend(
+        before
+            .iter()
+            .filter(|(name, _)| !after.iter().any(|(n, _)| n == name))
+            .map(|(name, version)| (name.as_str(), version.clone(), None)),
+    );
+    let rows = rows
+        .into_iter()
+        .map(|(name, old, new)| {
+            let delta = Delta::between(old.as_deref(), new.as_deref());
+            (name, old, new, delta)
+        })
+        .filter(|(_, _, _, delta)| !changed || *delta != Delta::Unchanged)
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Text => {
+            let width = rows.iter().map(|(n, _, _, _)| n.len()).max().unwrap_or(0);
+            let versions = rows
+                .iter()
+                .map(|(_, old, new, delta)| {
+                    let old = old.as_deref().unwrap_or("-");
+                    let new = new.as_deref().unwrap_or("-");
+                    if *delta == Delta::Unchanged {
+                        old.to_string()
+                    } else {
+                        format!("{old} -> {new}")
+                    }
+                })
+                .collect::<Vec<_>>();
+            let vwidth = versions.iter().map(String::len).max().unwrap_or(0);
+            for ((name, _, _, delta), versions) in rows.iter().zip(&versions) {
+                println!("{name:<width$}  {versions:<vwidth$}  {}", delta.name());
+            }
+        }
+        Format::Json => {
+            let crates = rows
+                .iter()
+                .map(|(name, old, new, delta)| {
+                    Json::object()
+                        .with("name", *name)
+                        .with("old", old.clone())
+                        .with("new", new.clone())
+                        .with("change", delta.name())
+                })
+                .collect::<Vec<_>>();
+            println!("{:#}", Json::from(crates));
+        }
+    }
+    Ok(())
+}
+
+/// Load the workspace from a directory if there is one by that name,
+/// or else from a git ref
+pub fn load(source: &str) -> Result<Workspace, Error> {
+    let dir = Path::new(source);
+    if dir.is_dir() {
+        return Workspace::read(
+            |path| Ok(std::fs::read_to_string(dir.join(path))?),
+            |subdir| workspace::list_dirs(dir, subdir),
+        )
+        .map_err(|e| e.context(format!("Can't load the workspace in {source}")));
+    }
+    git::git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{source}^{{commit}}"),
+    ])
+    .map_err(|_| anyhow!("{source} is neither a directory nor a git ref"))?;
+    // `ref:./path` is relative to the current directory, which is the
+    // workspace root even if that isn't the top of the repository
+    Workspace::read(
+        |path| {
+            let path = path.to_string_lossy().replace('\\', "/");
+            git::git(&["show", &format!("{source}:./{path}")])
+        },
+        |dir| {
+            let dir = dir.to_string_lossy().replace('\\', "/");
+            git::git(&["ls-tree", "-d", "--name-only", &format!("{source}:./{dir}")])
+                .map(|dirs| dirs.lines().map(OsString::from).collect())
+                .unwrap_or_default()
+        },
+    )
+    .map_err(|e| e.context(format!("Can't load the workspace at {source}")))
+}
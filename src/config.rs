@@ -0,0 +1,816 @@
+//! Settings read from the `[workspace.metadata.workspace-version]` table
+//! of the top level Cargo.toml
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Error};
+use toml_edit::{Document, Item, Value};
+
+use crate::json::Json;
+
+/// The name of the table inside `[workspace.metadata]` we read
+pub const TABLE: &str = "workspace-version";
+
+/// The version `dev` sets when `placeholder` isn't configured
+pub const DEFAULT_PLACEHOLDER: &str = "0.0.0-dev";
+
+/// The type of value a setting takes
+#[derive(Debug, Clone, Copy)]
+enum Type {
+    /// a path, or an array of paths, relative to the top level
+    Paths,
+    /// a single string
+    String,
+    /// an array of strings
+    Strings,
+    /// one of a fixed set of strings
+    Choice(&'static [&'static str]),
+    /// a process exit code, from 0 to 255
+    ExitCode,
+    /// a table whose values are each one of a fixed set of strings
+    ChoiceTable(&'static [&'static str]),
+    /// a table whose values are strings
+    StringTable,
+}
+
+/// Every setting we understand, with its type and a description
+///
+/// Both the validation in [`Config::from_document`] and the schema from
+/// [`schema`] come from this list, so they can't disagree
+const SETTINGS: &[(&str, Type, &str)] = &[
+    (
+        "pkgbuild",
+        Type::Paths,
+        "PKGBUILD files whose pkgver should track the workspace version",
+    ),
+    (
+        "wix",
+        Type::Paths,
+        "WiX sources (.wxs) or wix.toml files with the installer version",
+    ),
+    (
+        "maven",
+        Type::Paths,
+        "Maven pom.xml files whose project <version> should match",
+    ),
+    (
+        "gradle",
+        Type::Paths,
+        "gradle.properties files whose version= should match",
+    ),
+    (
+        "requirement-style",
+        Type::Choice(REQUIREMENT_STYLES),
+        "How requirements on other members are written: caret (1.2.3), tilde (~1.2.3), or exact (=1.2.3); if not set, requirements keep the =, ~ or ^ they have",
+    ),
+    (
+        "dependency-styles",
+        Type::ChoiceTable(REQUIREMENT_STYLES),
+        "The requirement style for particular members, by package name",
+    ),
+    (
+        "quote-style",
+        Type::Choice(QUOTE_STYLES),
+        "How versions we write are quoted: double, single, or preserve what was there",
+    ),
+    (
+        "inline-table-style",
+        Type::Choice(INLINE_TABLE_STYLES),
+        "Whether inline tables we change keep their spacing (preserve) or are reformatted (normalize)",
+    ),
+    (
+        "tag-prefix",
+        Type::String,
+        "What release tags have before the version, like v in v1.2.3",
+    ),
+    (
+        "skip",
+        Type::Strings,
+        "Package names of members whose version is managed separately",
+    ),
+    (
+        "minimum-version",
+        Type::String,
+        "The lowest version update and check will accept",
+    ),
+    (
+        "denied-versions",
+        Type::Strings,
+        "Versions that must never be used, such as ones burned by a botched release",
+    ),
+    (
+        "branch-policies",
+        Type::StringTable,
+        "The versions allowed on each branch, by branch pattern, like \"1.* stable\" or \"prerelease\"",
+    ),
+    (
+        "placeholder",
+        Type::String,
+        "The version dev sets between releases; 0.0.0-dev if not set",
+    ),
+    (
+        "pre-update-hook",
+        Type::Strings,
+        "A command to run before an update changes anything, as the program and its arguments",
+    ),
+    (
+        "post-update-hook",
+        Type::Strings,
+        "A command to run after an update, as the program and its arguments",
+    ),
+    (
+        "webhook",
+        Type::String,
+        "A URL to post to after an update, or $NAME to read it from the environment",
+    ),
+    (
+        "webhook-style",
+        Type::Choice(WEBHOOK_STYLES),
+        "The kind of payload the webhook expects",
+    ),
+    (
+        "webhook-template",
+        Type::String,
+        "JSON to post to the webhook, with {old}, {new}, {crates} and {tag_url} filled in",
+    ),
+    (
+        "exit-differences",
+        Type::ExitCode,
+        "The exit code when a check finds differences; 1 if not set",
+    ),
+    (
+        "exit-warnings",
+        Type::ExitCode,
+        "The exit code when there are only warnings; 0 if not set",
+    ),
+    (
+        "exit-error",
+        Type::ExitCode,
+        "The exit code when something goes wrong; 2 if not set",
+    ),
+];
+
+/// The values `requirement-style` can have
+const REQUIREMENT_STYLES: &[&str] = &["caret", "tilde", "exact"];
+
+/// How a requirement on another member is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequirementStyle {
+    /// `1.2.3`, which cargo treats as `^1.2.3`
+    Caret,
+    /// `~1.2.3`
+    Tilde,
+    /// `=1.2.3`
+    Exact,
+}
+
+impl RequirementStyle {
+    /// Read a style from its name in the configuration
+    fn from_name(name: &str) -> RequirementStyle {
+        match name {
+            "tilde" => RequirementStyle::Tilde,
+            "exact" => RequirementStyle::Exact,
+            _ => RequirementStyle::Caret,
+        }
+    }
+
+    /// The name of the style, as it is written in the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            RequirementStyle::Caret => "caret",
+            RequirementStyle::Tilde => "tilde",
+            RequirementStyle::Exact => "exact",
+        }
+    }
+
+    /// The operator a requirement in this style starts with
+    fn operator(self) -> &'static str {
+        match self {
+            RequirementStyle::Caret => "",
+            RequirementStyle::Tilde => "~",
+            RequirementStyle::Exact => "=",
+        }
+    }
+}
+
+/// The operator a requirement starts with, if it is one we keep: `=`,
+/// `~` or `^`
+///
+/// * `requirement` - the requirement, like `=1.2.3`
+pub fn operator(requirement: &str) -> &str {
+    let requirement = requirement.trim_start();
+    match requirement.chars().next() {
+        Some('=' | '~' | '^') => &requirement[..1],
+        _ => "",
+    }
+}
+
+/// The values `quote-style` can have
+const QUOTE_STYLES: &[&str] = &["double", "single", "preserve"];
+
+/// The values `inline-table-style` can have
+const INLINE_TABLE_STYLES: &[&str] = &["preserve", "normalize"];
+
+/// How the strings we write are quoted
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum QuoteStyle {
+    /// `"1.2.3"`
+    #[default]
+    Double,
+    /// `'1.2.3'`
+    Single,
+    /// whichever the value being replaced used
+    Preserve,
+}
+
+/// How the values we write are formatted, so we don't fight a formatter
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ValueStyle {
+    /// how strings are quoted
+    pub quotes: QuoteStyle,
+    /// reformat inline tables we change, like `{ path = "../a", version = "1.2.3" }`,
+    /// rather than keep the spacing they had
+    pub normalize_inline_tables: bool,
+}
+
+impl ValueStyle {
+    /// Make the string to replace a value with
+    ///
+    /// The spacing around the old value is kept. Strings that can't be
+    /// written in single quotes get double quotes whatever the style
+    ///
+    /// * `old` - the value being replaced
+    /// * `new` - the string to put in its place
+    pub fn string(&self, old: &Value, new: &str) -> Value {
+        let single = match self.quotes {
+            QuoteStyle::Double => false,
+            QuoteStyle::Single => true,
+            QuoteStyle::Preserve => match old {
+                Value::String(s) => s
+                    .as_repr()
+                    .and_then(|r| r.as_raw().as_str())
+                    .is_some_and(|r| r.starts_with('\'')),
+                _ => false,
+            },
+        };
+        let mut value = if single && !new.contains(['\'', '\n', '\r']) {
+            format!("'{new}'")
+                .parse::<Value>()
+                .unwrap_or_else(|_| Value::from(new))
+        } else {
+            Value::from(new)
+        };
+        *value.decor_mut() = old.decor().clone();
+        value
+    }
+}
+
+/// The versions a branch policy allows
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchPolicy {
+    /// the policy as written, for messages
+    pub text: String,
+    /// `Some(true)` for stable versions only, `Some(false)` for
+    /// pre-releases only
+    pub stable: Option<bool>,
+    /// the leading parts of the version that have to match, like
+    /// `[1]` for `1.*`; `None` for a part that can be anything
+    pub pattern: Vec<Option<u64>>,
+}
+
+impl BranchPolicy {
+    /// Read a policy like `1.* stable`, `prerelease`, or `2.1.*`
+    ///
+    /// * `text` - the words of the policy, separated by spaces
+    fn parse(text: &str) -> Result<BranchPolicy, String> {
+        let mut policy = BranchPolicy {
+            text: text.to_string(),
+            stable: None,
+            pattern: vec![],
+        };
+        for word in text.split_whitespace() {
+            match word {
+                "stable" => policy.stable = Some(true),
+                "prerelease" | "pre-release" => policy.stable = Some(false),
+                "*" | "any" => {}
+                _ => {
+                    policy.pattern = word
+                        .split('.')
+                        .map(|part| match part {
+                            "*" | "x" => Ok(None),
+                            _ => part.parse::<u64>().map(Some).map_err(|_| {
+                                format!(
+                                    "{word} isn't a version pattern like 1.* (or stable, or prerelease)"
+                                )
+                            }),
+                        })
+                        .collect::<Result<_, _>>()?;
+                    // trailing wildcards don't narrow anything
+                    while policy.pattern.last() == Some(&None) {
+                        policy.pattern.pop();
+                    }
+                }
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// The values `webhook-style` can have
+const WEBHOOK_STYLES: &[&str] = &["generic", "slack", "discord"];
+
+/// The kind of payload a webhook expects
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WebhookStyle {
+    /// a JSON object with each piece of information as a field
+    #[default]
+    Generic,
+    /// a Slack incoming webhook, which takes a `text` message
+    Slack,
+    /// a Discord webhook, which takes a `content` message
+    Discord,
+}
+
+/// Configuration for this tool, as found in the top level manifest
+#[derive(Debug, Default)]
+pub struct Config {
+    /// PKGBUILD files whose `pkgver` should track the workspace version
+    pub pkgbuild: Vec<PathBuf>,
+    /// WiX sources (`.wxs`) or `wix.toml` files with the installer version
+    pub wix: Vec<PathBuf>,
+    /// Maven `pom.xml` files whose project `<version>` should match
+    pub maven: Vec<PathBuf>,
+    /// `gradle.properties` files whose `version=` should match
+    pub gradle: Vec<PathBuf>,
+    /// how requirements on other members are written; if not set, they
+    /// keep the operator they have
+    pub requirement_style: Option<RequirementStyle>,
+    /// the requirement style for particular members, by package name
+    pub dependency_styles: Vec<(String, RequirementStyle)>,
+    /// how the values we write are formatted
+    pub value_style: ValueStyle,
+    /// what release tags have before the version; if not set, tags
+    /// may or may not start with `v`
+    pub tag_prefix: Option<String>,
+    /// package names of members we leave alone
+    pub skip: Vec<String>,
+    /// the lowest version we accept, as written in the configuration
+    pub minimum_version: Option<String>,
+    /// versions we refuse to use
+    pub denied_versions: Vec<String>,
+    /// the versions allowed on each branch, by branch pattern, in the
+    /// order they are listed
+    pub branch_policies: Vec<(String, BranchPolicy)>,
+    /// the version `dev` sets, if not the default one
+    pub placeholder: Option<String>,
+    /// the program and arguments to run before an update
+    pub pre_update_hook: Vec<String>,
+    /// the program and arguments to run after an update
+    pub post_update_hook: Vec<String>,
+    /// where to post after an update, as written in the configuration
+    pub webhook: Option<String>,
+    /// what the webhook expects to be posted
+    pub webhook_style: WebhookStyle,
+    /// a JSON template to post instead of the payload for the style
+    pub webhook_template: Option<String>,
+    /// the exit codes to use instead of the usual ones
+    pub exit_codes: ExitCodes,
+}
+
+/// Exit codes for each kind of outcome, where they have been changed
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExitCodes {
+    /// a check found values that don't match
+    pub differences: Option<u8>,
+    /// nothing is wrong, but there were warnings
+    pub warnings: Option<u8>,
+    /// something couldn't be done
+    pub error: Option<u8>,
+}
+
+impl Config {
+    /// Read the configuration from the top level manifest
+    ///
+    /// A missing `[workspace.metadata.workspace-version]` table is not
+    /// an error; it just means everything is left at the defaults. Keys
+    /// we don't know about are an error, since they are most likely a
+    /// typo that would otherwise be silently ignored
+    ///
+    /// * `doc` - the parsed top level Cargo.toml
+    pub fn from_document(doc: &Document) -> Result<Config, Error> {
+        let mut config = Config::default();
+        let Some(table) = doc
+            .get("workspace")
+            .and_then(|w| w.get("metadata"))
+            .and_then(|m| m.get(TABLE))
+        else {
+            return Ok(config);
+        };
+        let Some(keys) = table.as_table_like() else {
+            bail!("[workspace.metadata.{TABLE}] must be a table")
+        };
+        for (key, _) in keys.iter() {
+            if SETTINGS.iter().any(|(name, _, _)| *name == key) {
+                continue;
+            }
+            match SETTINGS
+                .iter()
+                .find(|(name, _, _)| distance(name, key) <= 2)
+            {
+                Some((name, _, _)) => {
+                    bail!("Unknown key {key} in [workspace.metadata.{TABLE}]; did you mean {name}?")
+                }
+                None => bail!(
+                    "Unknown key {key} in [workspace.metadata.{TABLE}]; the known keys are {}",
+                    SETTINGS
+                        .iter()
+                        .map(|(name, _, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+        if let Some(item) = table.get("pkgbuild") {
+            config.pkgbuild = paths(item, "pkgbuild")?;
+        }
+        if let Some(item) = table.get("wix") {
+            config.wix = paths(item, "wix")?;
+        }
+        if let Some(item) = table.get("maven") {
+            config.maven = paths(item, "maven")?;
+        }
+        if let Some(item) = table.get("gradle") {
+            config.gradle = paths(item, "gradle")?;
+        }
+        if let Some(item) = table.get("requirement-style") {
+            config.requirement_style = Some(RequirementStyle::from_name(choice(
+                item,
+                "requirement-style",
+                REQUIREMENT_STYLES,
+            )?));
+        }
+        if let Some(item) = table.get("dependency-styles") {
+            config.dependency_styles = choice_table(item, "dependency-styles", REQUIREMENT_STYLES)?
+                .into_iter()
+                .map(|(name, style)| (name, RequirementStyle::from_name(style)))
+                .collect();
+        }
+        if let Some(item) = table.get("quote-style") {
+            config.value_style.quotes = match choice(item, "quote-style", QUOTE_STYLES)? {
+                "single" => QuoteStyle::Single,
+                "preserve" => QuoteStyle::Preserve,
+                _ => QuoteStyle::Double,
+            };
+        }
+        if let Some(item) = table.get("inline-table-style") {
+            config.value_style.normalize_inline_tables =
+                choice(item, "inline-table-style", INLINE_TABLE_STYLES)? == "normalize";
+        }
+        if let Some(item) = table.get("tag-prefix") {
+            config.tag_prefix = Some(string(item, "tag-prefix")?);
+        }
+        if let Some(item) = table.get("skip") {
+            config.skip = strings(item, "skip")?;
+        }
+        if let Some(item) = table.get("minimum-version") {
+            config.minimum_version = Some(string(item, "minimum-version")?);
+        }
+        if let Some(item) = table.get("denied-versions") {
+            config.denied_versions = strings(item, "denied-versions")?;
+        }
+        if let Some(item) = table.get("branch-policies") {
+            config.branch_policies = string_table(item, "branch-policies")?
+                .into_iter()
+                .map(|(branch, text)| {
+                    let policy = BranchPolicy::parse(&text).map_err(|e| {
+                        anyhow!("branch-policies.{branch} in [workspace.metadata.{TABLE}]: {e}")
+                    })?;
+                    Ok((branch, policy))
+                })
+                .collect::<Result<_, Error>>()?;
+        }
+        if let Some(item) = table.get("placeholder") {
+            config.placeholder = Some(string(item, "placeholder")?);
+        }
+        if let Some(item) = table.get("pre-update-hook") {
+            config.pre_update_hook = strings(item, "pre-update-hook")?;
+        }
+        if let Some(item) = table.get("post-update-hook") {
+            config.post_update_hook = strings(item, "post-update-hook")?;
+        }
+        if let Some(item) = table.get("webhook") {
+            config.webhook = Some(string(item, "webhook")?);
+        }
+        if let Some(item) = table.get("webhook-style") {
+            config.webhook_style = match choice(item, "webhook-style", WEBHOOK_STYLES)? {
+                "slack" => WebhookStyle::Slack,
+                "discord" => WebhookStyle::Discord,
+                _ => WebhookStyle::Generic,
+            };
+        }
+        if let Some(item) = table.get("webhook-template") {
+            config.webhook_template = Some(string(item, "webhook-template")?);
+        }
+        if let Some(item) = table.get("exit-differences") {
+            config.exit_codes.differences = Some(exit_code(item, "exit-differences")?);
+        }
+        if let Some(item) = table.get("exit-warnings") {
+            config.exit_codes.warnings = Some(exit_code(item, "exit-warnings")?);
+        }
+        if let Some(item) = table.get("exit-error") {
+            config.exit_codes.error = Some(exit_code(item, "exit-error")?);
+        }
+        Ok(config)
+    }
+
+    /// The requirement style for dependencies on a member, if one is
+    /// configured
+    ///
+    /// * `package` - the package name of the member
+    pub fn style_for(&self, package: &str) -> Option<RequirementStyle> {
+        self.dependency_styles
+            .iter()
+            .find(|(name, _)| name == package)
+            .map(|(_, style)| *style)
+            .or(self.requirement_style)
+    }
+
+    /// The operator a requirement on a member should start with: the
+    /// one of the configured style, or else the one it already has
+    ///
+    /// * `package` - the package name of the member
+    /// * `existing` - the requirement the dependency has now, if any
+    pub fn operator_for<'a>(&self, package: &str, existing: Option<&'a str>) -> &'a str {
+        match self.style_for(package) {
+            Some(style) => style.operator(),
+            None => existing.map_or("", operator),
+        }
+    }
+
+    /// The requirement dependencies on a member should have
+    ///
+    /// * `package` - the package name of the member
+    /// * `version` - the version of the member
+    /// * `existing` - the requirement the dependency has now, if any,
+    ///   whose operator is kept when no style is configured
+    pub fn requirement(&self, package: &str, version: &str, existing: Option<&str>) -> String {
+        format!("{}{version}", self.operator_for(package, existing))
+    }
+
+    /// The version the workspace has between releases
+    pub fn placeholder(&self) -> &str {
+        self.placeholder.as_deref().unwrap_or(DEFAULT_PLACEHOLDER)
+    }
+
+    /// The version a git tag is for, if it is a release tag
+    ///
+    /// * `tag` - the name of the tag, like `v1.2.3`
+    pub fn tag_version<'a>(&self, tag: &'a str) -> Option<&'a str> {
+        match &self.tag_prefix {
+            Some(prefix) => tag.strip_prefix(prefix.as_str()),
+            None => Some(tag.strip_prefix('v').unwrap_or(tag)),
+        }
+    }
+}
+
+/// Read a setting that is either a single path or an array of paths
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+fn paths(item: &Item, key: &str) -> Result<Vec<PathBuf>, Error> {
+    if let Some(path) = item.as_str() {
+        return Ok(vec![PathBuf::from(path)]);
+    }
+    let Some(array) = item.as_array() else {
+        bail!(
+            "{key} in [workspace.metadata.{TABLE}] must be a string or an array of strings, not {}",
+            item.type_name()
+        )
+    };
+    array
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            v.as_str().map(PathBuf::from).ok_or(anyhow!(
+                "{key}[{i}] in [workspace.metadata.{TABLE}] must be a string, not {}",
+                v.type_name()
+            ))
+        })
+        .collect()
+}
+
+/// Read a setting that is a single string
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+fn string(item: &Item, key: &str) -> Result<String, Error> {
+    item.as_str().map(String::from).ok_or(anyhow!(
+        "{key} in [workspace.metadata.{TABLE}] must be a string, not {}",
+        item.type_name()
+    ))
+}
+
+/// Read a setting that is one of a fixed set of strings
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+/// * `choices` - the values it may have
+fn choice<'a>(item: &'a Item, key: &str, choices: &[&str]) -> Result<&'a str, Error> {
+    let value = item.as_str().ok_or(anyhow!(
+        "{key} in [workspace.metadata.{TABLE}] must be a string, not {}",
+        item.type_name()
+    ))?;
+    if !choices.contains(&value) {
+        bail!(
+            "{key} in [workspace.metadata.{TABLE}] must be one of {}, not {value:?}",
+            choices.join(", ")
+        )
+    }
+    Ok(value)
+}
+
+/// Read a setting that is a table of names, each with one of a fixed set
+/// of strings
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+/// * `choices` - the values each entry may have
+fn choice_table<'a>(
+    item: &'a Item,
+    key: &str,
+    choices: &[&str],
+) -> Result<Vec<(String, &'a str)>, Error> {
+    let Some(table) = item.as_table_like() else {
+        bail!(
+            "{key} in [workspace.metadata.{TABLE}] must be a table, not {}",
+            item.type_name()
+        )
+    };
+    table
+        .iter()
+        .map(|(name, value)| {
+            let value = choice(value, &format!("{key}.{name}"), choices)?;
+            Ok((name.to_string(), value))
+        })
+        .collect()
+}
+
+/// Read a setting that is a table of strings
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+fn string_table(item: &Item, key: &str) -> Result<Vec<(String, String)>, Error> {
+    let Some(table) = item.as_table_like() else {
+        bail!(
+            "{key} in [workspace.metadata.{TABLE}] must be a table, not {}",
+            item.type_name()
+        )
+    };
+    table
+        .iter()
+        .map(|(name, value)| Ok((name.to_string(), string(value, &format!("{key}.{name}"))?)))
+        .collect()
+}
+
+/// Read a setting that is a process exit code
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+fn exit_code(item: &Item, key: &str) -> Result<u8, Error> {
+    item.as_integer()
+        .and_then(|code| u8::try_from(code).ok())
+        .ok_or(anyhow!(
+            "{key} in [workspace.metadata.{TABLE}] must be a number from 0 to 255"
+        ))
+}
+
+/// Read a setting that is an array of strings
+///
+/// * `item` - the value from the configuration table
+/// * `key` - the name of the setting, for error messages
+fn strings(item: &Item, key: &str) -> Result<Vec<String>, Error> {
+    let Some(array) = item.as_array() else {
+        bail!(
+            "{key} in [workspace.metadata.{TABLE}] must be an array of strings, not {}",
+            item.type_name()
+        )
+    };
+    array
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            v.as_str().map(String::from).ok_or(anyhow!(
+                "{key}[{i}] in [workspace.metadata.{TABLE}] must be a string, not {}",
+                v.type_name()
+            ))
+        })
+        .collect()
+}
+
+/// The number of single character edits to turn `a` into `b`, for
+/// suggesting what a misspelled key was meant to be
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// A JSON Schema describing the configuration table, for editors that
+/// can validate and complete `Cargo.toml`
+pub fn schema() -> Json {
+    let properties = SETTINGS
+        .iter()
+        .map(|(name, kind, description)| {
+            let schema = match kind {
+                Type::Paths => Json::object().with(
+                    "anyOf",
+                    vec![
+                        Json::object().with("type", "string"),
+                        Json::object()
+                            .with("type", "array")
+                            .with("items", Json::object().with("type", "string")),
+                    ],
+                ),
+                Type::String => Json::object().with("type", "string"),
+                Type::Strings => Json::object()
+                    .with("type", "array")
+                    .with("items", Json::object().with("type", "string")),
+                Type::Choice(choices) => Json::object().with("enum", choices.to_vec()),
+                Type::ChoiceTable(choices) => Json::object().with("type", "object").with(
+                    "additionalProperties",
+                    Json::object().with("enum", choices.to_vec()),
+                ),
+                Type::StringTable => Json::object().with("type", "object").with(
+                    "additionalProperties",
+                    Json::object().with("type", "string"),
+                ),
+                Type::ExitCode => Json::object()
+                    .with("type", "integer")
+                    .with("minimum", 0usize)
+                    .with("maximum", 255usize),
+            };
+            (name.to_string(), schema.with("description", *description))
+        })
+        .collect();
+    Json::object()
+        .with("$schema", "http://json-schema.org/draft-07/schema#")
+        .with("title", format!("[workspace.metadata.{TABLE}]"))
+        .with(
+            "description",
+            "Settings for cargo workspace-version, in the top level Cargo.toml",
+        )
+        .with("type", "object")
+        .with("properties", Json::Object(properties))
+        .with("additionalProperties", false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(settings: &str) -> Config {
+        let doc = format!("[workspace.metadata.{TABLE}]\n{settings}")
+            .parse()
+            .unwrap();
+        Config::from_document(&doc).unwrap()
+    }
+
+    #[test]
+    fn requirement_keeps_operator() {
+        let config = config("");
+        let cases = [
+            (None, "2.0.0"),
+            (Some("1.0.0"), "2.0.0"),
+            (Some("=1.0.0"), "=2.0.0"),
+            (Some("~1.0.0"), "~2.0.0"),
+            (Some("^1.0.0"), "^2.0.0"),
+            (Some(">=1.0.0"), "2.0.0"),
+        ];
+        for (existing, want) in cases {
+            assert_eq!(
+                config.requirement("b", "2.0.0", existing),
+                want,
+                "{existing:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn requirement_in_configured_style() {
+        let config = config("requirement-style = \"exact\"\ndependency-styles = { c = \"tilde\" }");
+        assert_eq!(config.requirement("b", "2.0.0", Some("^1.0.0")), "=2.0.0");
+        assert_eq!(config.requirement("c", "2.0.0", Some("=1.0.0")), "~2.0.0");
+        let config = self::config("requirement-style = \"caret\"");
+        assert_eq!(config.requirement("b", "2.0.0", Some("=1.0.0")), "2.0.0");
+    }
+}
@@ -0,0 +1,121 @@
+//! The `dep-update` subcommand: move the `rev` pin of a git
+//! dependency to the latest commit of its repository
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Error};
+use toml_edit::{Document, Item};
+
+use crate::{
+    finish_file, git, report,
+    workspace::{dependency_package, visit_dependency_tables, Workspace},
+    Args,
+};
+
+/// Rewrite the `rev` of every entry for a git dependency
+///
+/// Every manifest in the workspace is searched for dependencies on the
+/// named package that come from git. The commit is resolved once with
+/// `git ls-remote` and written to each entry that pins a `rev`; other
+/// keys in the entry are left as they were
+///
+/// * `ws` - the workspace to work on
+/// * `name` - the package name of the git dependency
+/// * `reference` - the branch or tag to resolve, or the default branch
+/// * `opts` - the command line arguments passed in
+pub fn run(
+    ws: &mut Workspace,
+    name: &str,
+    reference: Option<&str>,
+    opts: &Args,
+) -> Result<(), Error> {
+    // the top level manifest might also be a member; don't do it twice
+    let root_is_member = ws.members.iter().any(|m| m.dir == ".");
+    let mut docs: Vec<(PathBuf, &mut Document)> = vec![];
+    if !root_is_member {
+        docs.push((ws.manifest.clone(), &mut ws.doc));
+    }
+    for member in &mut ws.members {
+        docs.push((member.manifest.clone(), &mut member.doc));
+    }
+
+    // find the repository; every entry has to agree on where it is
+    let mut urls = vec![];
+    for (_, doc) in &mut docs {
+        visit_dependency_tables(doc, |_, table| {
+            for (key, dep) in table.iter() {
+                if dependency_package(key, dep) == name {
+                    if let Some(url) = dep.get("git").and_then(Item::as_str) {
+                        if !urls.iter().any(|u| u == url) {
+                            urls.push(url.to_string());
+                        }
+                    }
+                }
+            }
+        });
+    }
+    let url = match &urls[..] {
+        [] => bail!("There is no git dependency on {name} in this workspace"),
+        [url] => url,
+        _ => bail!(
+            "The git dependencies on {name} don't agree on a repository: {}",
+            urls.join(", ")
+        ),
+    };
+    let reference = reference.unwrap_or("HEAD");
+    let sha = git::ls_remote(url, reference)
+        .map_err(|e| anyhow!("Can't find {reference} of {name}: {e}"))?;
+    if !opts.quiet {
+        println!("{reference} of {url} is {sha}");
+    }
+
+    let style = ws.config.value_style;
+    for (path, doc) in &mut docs {
+        let mut changed = false;
+        visit_dependency_tables(doc, |keys, table| {
+            let where_ = format!("[{}]", report::toml_path(keys));
+            for (key, dep) in table.iter_mut() {
+                if dependency_package(key.get(), dep) != name || dep.get("git").is_none() {
+                    continue;
+                }
+                let Some(rev) = dep.get_mut("rev").and_then(Item::as_value_mut) else {
+                    opts.explain(format_args!(
+                        "Skipped dependency {} in {where_} of {}: it isn't pinned with rev",
+                        key.get(),
+                        path.display()
+                    ));
+                    continue;
+                };
+                let old = rev.as_str().unwrap_or_default().to_string();
+                if old == sha {
+                    continue;
+                }
+                let toml_path = report::toml_path(&[keys, &[key.get(), "rev"]].concat());
+                opts.difference(
+                    report::Change {
+                        path: path.clone(),
+                        toml_path: Some(toml_path),
+                        key: key.get().to_string(),
+                        old: old.clone(),
+                        new: sha.clone(),
+                    },
+                    format!(
+                        "Rev for dependency for {} in {where_} of {} was {old} want {sha}",
+                        key.get(),
+                        path.display(),
+                    ),
+                );
+                *rev = style.string(rev, &sha);
+                if style.normalize_inline_tables {
+                    if let Some(dep) = dep.as_inline_table_mut() {
+                        dep.fmt();
+                    }
+                }
+                changed = true;
+            }
+        });
+        if changed {
+            finish_file(path, doc.to_string(), opts)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! The development placeholder: the version a workspace has between
+//! releases, for teams that only stamp real versions when releasing
+use anyhow::Error;
+
+use crate::{
+    report::DifferencesFound,
+    workspace::{DeclaredVersion, Workspace},
+};
+
+/// Fail if the workspace, or any member, still has the placeholder
+///
+/// Members that inherit their version are covered by
+/// `[workspace.package]`, and members that are skipped are left out,
+/// since their version is managed separately
+///
+/// * `ws` - the workspace to look at
+/// * `quiet` - don't say that everything is fine
+pub fn check_not_placeholder(ws: &Workspace, quiet: bool) -> Result<(), Error> {
+    let placeholder = ws.config.placeholder();
+    let mut found = vec![];
+    if ws.package_version() == Some(placeholder) {
+        found.push("[workspace.package]".to_string());
+    }
+    for member in &ws.members {
+        if member.declared_version() != DeclaredVersion::Version(placeholder) {
+            continue;
+        }
+        match member.name() {
+            Some(name) if ws.config.skip.iter().any(|s| s == name) => {}
+            Some(name) => found.push(name.to_string()),
+            None => found.push(member.manifest.display().to_string()),
+        }
+    }
+    if !found.is_empty() {
+        return Err(DifferencesFound(format!(
+            "{} still have the development placeholder {placeholder}; \
+             set a real version before releasing",
+            found.join(", ")
+        ))
+        .into());
+    }
+    if !quiet {
+        println!("No versions are the development placeholder {placeholder}");
+    }
+    Ok(())
+}
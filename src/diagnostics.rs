@@ -0,0 +1,182 @@
+//! The results of `check` or `update` as cargo's JSON messages, like
+//! `cargo build --message-format json` prints, so editors and other
+//! tools that already read those can show them in the manifest
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+
+use crate::{
+    json::Json,
+    report::{self, CrateSummary, Difference},
+    workspace::Workspace,
+};
+
+/// Render the results as one JSON message per line
+///
+/// Each difference is a `compiler-message` with a span on the old
+/// value and, for a check, the new one as a suggested replacement.
+/// The last line is `build-finished`, as with cargo
+///
+/// * `ws` - the workspace, for the package each file belongs to
+/// * `writing` - whether the values were changed, or only checked
+/// * `differences` - everything that didn't have the version
+/// * `error` - why the run failed, if it did for some other reason
+pub fn render(
+    ws: &Workspace,
+    writing: bool,
+    differences: &[Difference],
+    error: Option<&Error>,
+) -> String {
+    let root = std::env::current_dir().unwrap_or_default();
+    let crates = report::crates(ws, differences);
+    let mut out = String::new();
+    for d in differences {
+        let (level, label) = if writing {
+            ("warning", format!("changed to {}", d.change.new))
+        } else {
+            ("error", format!("should be {}", d.change.new))
+        };
+        let end = d.column + d.change.old.chars().count();
+        let mut span = Json::object()
+            .with("file_name", report::display_path(&d.change.path))
+            .with("byte_start", d.offset)
+            .with("byte_end", d.offset + d.change.old.len())
+            .with("line_start", d.line)
+            .with("line_end", d.line)
+            .with("column_start", d.column)
+            .with("column_end", end)
+            .with("is_primary", true)
+            .with(
+                "text",
+                vec![Json::object()
+                    .with("text", d.text.as_str())
+                    .with("highlight_start", d.column)
+                    .with("highlight_end", end)],
+            )
+            .with("label", label.as_str())
+            .with("expansion", Json::Null);
+        // removing a value takes more than replacing the text in the span
+        span = if writing || d.change.new.is_empty() {
+            span.with("suggested_replacement", Json::Null)
+                .with("suggestion_applicability", Json::Null)
+        } else {
+            span.with("suggested_replacement", d.change.new.as_str())
+                .with("suggestion_applicability", "MachineApplicable")
+        };
+        let rendered = rendered(level, &d.message, d, &label);
+        out.push_str(&message(
+            &package(ws, &root, &crates, &d.change.path),
+            level,
+            &d.message,
+            vec![span],
+            &rendered,
+        ));
+        out.push('\n');
+    }
+    if let Some(e) = error.filter(|e| !e.is::<report::DifferencesFound>()) {
+        let text = format!("{e:#}");
+        let rendered = format!("error: {text}\n");
+        out.push_str(&message(
+            &package(ws, &root, &crates, &ws.manifest),
+            "error",
+            &text,
+            vec![],
+            &rendered,
+        ));
+        out.push('\n');
+    }
+    let success = error.is_none();
+    out.push_str(
+        &Json::object()
+            .with("reason", "build-finished")
+            .with("success", success)
+            .to_string(),
+    );
+    out.push('\n');
+    out
+}
+
+/// The text cargo would print for a diagnostic, which tools show
+/// as is
+fn rendered(level: &str, text: &str, d: &Difference, label: &str) -> String {
+    let number = d.line.to_string();
+    let gutter = " ".repeat(number.len());
+    let marks = "^".repeat(d.change.old.chars().count().max(1));
+    format!(
+        "{level}: {text}\n{gutter}--> {}:{}:{}\n{gutter} |\n{number} | {}\n{gutter} | {}{marks} {label}\n\n",
+        report::display_path(&d.change.path),
+        d.line,
+        d.column,
+        d.text,
+        " ".repeat(d.column - 1),
+    )
+}
+
+/// The package a message is for, as cargo describes it
+struct Package {
+    /// the package name
+    name: String,
+    /// the package ID, as `cargo metadata` gives it
+    id: String,
+    /// the absolute path of its manifest
+    manifest: String,
+}
+
+/// Find the package a file belongs to
+///
+/// Files that aren't the manifest of a member, like the top level
+/// manifest of a virtual workspace or an extra file, are put on the
+/// root package if there is one, or else the first member. The
+/// version is the one in the manifest before any update, which is
+/// what cargo would see for a check
+fn package(ws: &Workspace, root: &Path, crates: &[CrateSummary<'_>], path: &Path) -> Package {
+    let member = ws
+        .members
+        .iter()
+        .find(|m| m.manifest == path)
+        .or_else(|| ws.members.iter().find(|m| m.dir == "."))
+        .or_else(|| ws.members.first());
+    let name = member.and_then(|m| m.name()).unwrap_or_default();
+    let version = crates
+        .iter()
+        .find(|c| c.name == name)
+        .and_then(|c| c.old.as_deref().or(c.new))
+        .unwrap_or_default();
+    let manifest = member.map_or_else(|| root.join(&ws.manifest), |m| root.join(&m.manifest));
+    let dir = manifest.parent().map(PathBuf::from).unwrap_or_default();
+    Package {
+        name: name.to_string(),
+        id: format!("path+file://{}#{name}@{version}", dir.display()),
+        manifest: manifest.display().to_string(),
+    }
+}
+
+/// One `compiler-message`, for a package
+fn message(package: &Package, level: &str, text: &str, spans: Vec<Json>, rendered: &str) -> String {
+    let target = Json::object()
+        .with("kind", vec!["lib"])
+        .with("crate_types", vec!["lib"])
+        .with("name", package.name.replace('-', "_"))
+        .with("src_path", package.manifest.as_str())
+        .with("edition", "2021")
+        .with("doc", false)
+        .with("doctest", false)
+        .with("test", false);
+    Json::object()
+        .with("reason", "compiler-message")
+        .with("package_id", package.id.as_str())
+        .with("manifest_path", package.manifest.as_str())
+        .with("target", target)
+        .with(
+            "message",
+            Json::object()
+                .with("$message_type", "diagnostic")
+                .with("message", text)
+                .with("code", Json::Null)
+                .with("level", level)
+                .with("spans", spans)
+                .with("children", Vec::<Json>::new())
+                .with("rendered", rendered),
+        )
+        .to_string()
+}
@@ -0,0 +1,140 @@
+//! Unified diffs of what an update would write, like `diff -u` prints,
+//! so a failed check shows exactly what has to change
+use std::path::Path;
+
+use crate::report::display_path;
+
+/// Lines of unchanged text shown around each change
+const CONTEXT: usize = 3;
+
+/// One line of the diff
+#[derive(Debug, Clone, Copy)]
+enum Line<'a> {
+    /// in both versions of the file
+    Same(&'a str),
+    /// only in the current file
+    Removed(&'a str),
+    /// only in the new file
+    Added(&'a str),
+}
+
+/// The unified diff between the current and new contents of a file
+///
+/// Empty if they have the same lines
+///
+/// * `path` - the file, for the `---` and `+++` lines
+/// * `old` - what the file has now
+/// * `new` - what it would be changed to
+pub fn unified(path: &Path, old: &str, new: &str) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let lines = lines(&old, &new);
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    if changed.is_empty() {
+        return String::new();
+    }
+    let name = display_path(path);
+    let mut out = format!("--- a/{name}\n+++ b/{name}\n");
+    let mut next = 0;
+    while next < changed.len() {
+        // changes close enough together share a hunk, as with diff -u
+        let first = changed[next];
+        let mut last = first;
+        while next + 1 < changed.len() && changed[next + 1] - last <= 2 * CONTEXT {
+            next += 1;
+            last = changed[next];
+        }
+        next += 1;
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(lines.len());
+        let (mut old_line, mut new_line) = (1, 1);
+        for line in &lines[..start] {
+            match line {
+                Line::Same(_) => (old_line, new_line) = (old_line + 1, new_line + 1),
+                Line::Removed(_) => old_line += 1,
+                Line::Added(_) => new_line += 1,
+            }
+        }
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|l| !matches!(l, Line::Added(_))).count();
+        let new_count = hunk
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        // an empty side is said to start at the line before
+        let from = |line: usize, count: usize| if count == 0 { line - 1 } else { line };
+        out.push_str(&format!(
+            "@@ -{},{old_count} +{},{new_count} @@\n",
+            from(old_line, old_count),
+            from(new_line, new_count)
+        ));
+        for line in hunk {
+            let (mark, text) = match line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            out.push(mark);
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Every line of both files, in order, from the longest run of lines
+/// they have in common
+///
+/// The lines at the start and end that are the same are taken off
+/// first, since an update only changes a few lines in between
+fn lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    // common[i][j] is the length of the longest common run of lines
+    // in a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut lines = old[..prefix]
+        .iter()
+        .map(|line| Line::Same(line))
+        .collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(Line::Same(a[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < a.len() && (j == b.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(Line::Removed(a[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(b[j]));
+            j += 1;
+        }
+    }
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    lines
+}
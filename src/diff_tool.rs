@@ -0,0 +1,57 @@
+//! Showing what would change in a file with an external diff viewer,
+//! like delta or difftastic
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Error};
+
+/// Run the diff tool on the current and new contents of a file
+///
+/// Both sides are written to a temporary directory, under `a/` and
+/// `b/` with the file's own path, so the tool shows which file it is
+/// and tools that pick a syntax by the name still can. The tool's exit status is ignored, since most of
+/// them fail when the files differ, which they always do here
+///
+/// * `tool` - the program followed by any arguments, split on spaces;
+///   the two files are added after them
+/// * `path` - the file that would be changed
+/// * `contents` - what the file would be changed to
+pub fn show(tool: &str, path: &Path, contents: &str) -> Result<(), Error> {
+    let mut words = tool.split_whitespace();
+    let Some(program) = words.next() else {
+        bail!("--diff-tool needs a command")
+    };
+    let current = fs::read_to_string(path).context(format!("Can't read {}", path.display()))?;
+    let dir = std::env::temp_dir().join(format!("cargo-workspace-version-{}", std::process::id()));
+    let result = write_sides(&dir, path, &current, contents).and_then(|(old, new)| {
+        Command::new(program)
+            .args(words)
+            .arg(old)
+            .arg(new)
+            .status()
+            .context(format!("Can't run the diff tool {program}"))
+    });
+    let _ = fs::remove_dir_all(&dir);
+    result.map(|_| ())
+}
+
+/// Write the two sides of the diff, returning where they went
+fn write_sides(dir: &Path, path: &Path, old: &str, new: &str) -> Result<(PathBuf, PathBuf), Error> {
+    // only the plain parts, so nothing ends up outside the directory
+    let relative = path
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect::<PathBuf>();
+    let sides = [dir.join("a").join(&relative), dir.join("b").join(&relative)];
+    for (side, contents) in sides.iter().zip([old, new]) {
+        if let Some(parent) = side.parent() {
+            fs::create_dir_all(parent).context(format!("Can't create {}", parent.display()))?;
+        }
+        fs::write(side, contents).context(format!("Can't write {}", side.display()))?;
+    }
+    let [old, new] = sides;
+    Ok((old, new))
+}
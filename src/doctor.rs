@@ -0,0 +1,369 @@
+//! The `doctor` subcommand: find problems with the workspace that
+//! would stop the other commands from working, and suggest fixes
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Error};
+use toml_edit::{Array, Document, Item};
+
+use crate::{
+    config::Config,
+    encoding,
+    workspace::{excluded_dirs, expand_member, is_glob, is_inherited, list_dirs, normalize_member},
+};
+
+/// How bad a problem is
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Severity {
+    /// things will work, but probably not the way you want
+    Warning,
+    /// the other commands will fail or skip something
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// One problem, and what to do about it
+#[derive(Debug)]
+struct Finding {
+    severity: Severity,
+    problem: String,
+    fix: String,
+}
+
+/// Collects findings as the checks run
+#[derive(Debug, Default)]
+struct Findings(Vec<Finding>);
+
+impl Findings {
+    fn error<P: Into<String>, F: Into<String>>(&mut self, problem: P, fix: F) {
+        self.0.push(Finding {
+            severity: Severity::Error,
+            problem: problem.into(),
+            fix: fix.into(),
+        });
+    }
+
+    fn warning<P: Into<String>, F: Into<String>>(&mut self, problem: P, fix: F) {
+        self.0.push(Finding {
+            severity: Severity::Warning,
+            problem: problem.into(),
+            fix: fix.into(),
+        });
+    }
+}
+
+/// How a member declares its version, for spotting mixed styles
+#[derive(Debug)]
+enum Style {
+    /// `version = "..."`
+    Explicit(String),
+    /// `version.workspace = true`
+    Inherited,
+}
+
+/// Run all the checks on the workspace in the current directory
+///
+/// Unlike the other commands, this doesn't stop at the first problem;
+/// every problem found is printed along with a suggested fix
+///
+/// Fails if any of the problems would stop the other commands working,
+/// and otherwise returns true if there were warnings
+pub fn run(quiet: bool) -> Result<bool, Error> {
+    let findings = diagnose();
+    if !quiet {
+        for finding in &findings.0 {
+            println!("{}: {}", finding.severity, finding.problem);
+            println!("  fix: {}", finding.fix);
+        }
+    }
+    let errors = findings
+        .0
+        .iter()
+        .filter(|f| f.severity == Severity::Error)
+        .count();
+    let warnings = findings.0.len() - errors;
+    if errors > 0 {
+        bail!("Found {errors} error(s) and {warnings} warning(s)")
+    }
+    if !quiet {
+        if warnings > 0 {
+            println!("Found {warnings} warning(s)");
+        } else {
+            println!("No problems found");
+        }
+    }
+    Ok(warnings > 0)
+}
+
+/// Run every check, collecting what we find
+fn diagnose() -> Findings {
+    let mut findings = Findings::default();
+
+    let root = match std::fs::read_to_string("Cargo.toml") {
+        Ok(root) => root,
+        Err(e) => {
+            findings.error(
+                format!("Can't read Cargo.toml: {e}"),
+                "run this from the top level directory of the workspace",
+            );
+            return findings;
+        }
+    };
+    let doc = match encoding::without_bom(&root).parse::<Document>() {
+        Ok(doc) => doc,
+        Err(e) => {
+            findings.error(
+                format!("Cargo.toml isn't valid TOML: {e}"),
+                "fix the syntax error; cargo will report the same problem",
+            );
+            return findings;
+        }
+    };
+    if let Err(e) = Config::from_document(&doc) {
+        findings.error(
+            format!("Bad configuration: {e}"),
+            "correct the [workspace.metadata.workspace-version] table",
+        );
+    }
+    let Some(workspace) = doc.get("workspace") else {
+        findings.error(
+            "No [workspace] section in Cargo.toml",
+            "add a [workspace] section with a members array listing each crate",
+        );
+        return findings;
+    };
+    // as in cargo, a root package can leave the list out
+    let no_entries = Array::new();
+    let entries = match workspace.get("members") {
+        Some(entries) => {
+            let Some(entries) = entries.as_array() else {
+                findings.error(
+                    "members in the [workspace] section isn't an array",
+                    "write members as an array of paths, like members = [\"crate-a\"]",
+                );
+                return findings;
+            };
+            entries
+        }
+        None if doc.contains_key("package") => &no_entries,
+        None => {
+            findings.error(
+                "No members in the [workspace] section",
+                "add members = [\"path/to/crate\", ...] listing each crate",
+            );
+            return findings;
+        }
+    };
+
+    let workspace_version = workspace
+        .get("package")
+        .and_then(|p| p.get("version"))
+        .and_then(Item::as_str);
+    let mut styles = vec![];
+    let mut seen = HashSet::new();
+    let exclude = match excluded_dirs(&doc) {
+        Ok(exclude) => exclude,
+        Err(e) => {
+            findings.error(
+                format!("{e}"),
+                "write exclude as an array of paths, like exclude = [\"examples\"]",
+            );
+            vec![]
+        }
+    };
+    // each directory, and whether a glob pattern matched it
+    let mut dirs = vec![];
+    for entry in entries {
+        let Some(dir) = entry.as_str() else {
+            findings.error(
+                format!(
+                    "The member entry {} isn't a string",
+                    entry.to_string().trim()
+                ),
+                "write each member as a quoted path",
+            );
+            continue;
+        };
+        if !is_glob(dir) {
+            dirs.push((dir.to_string(), false));
+            continue;
+        }
+        let mut unusable = vec![];
+        let found = expand_member(
+            dir,
+            &exclude,
+            &|d: &Path| list_dirs(Path::new("."), d),
+            &mut unusable,
+        );
+        for e in unusable {
+            findings.error(format!("{e}"), "rename the directory, so its name is UTF-8");
+        }
+        if found.is_empty() {
+            findings.warning(
+                format!("The member entry {dir:?} doesn't match any directory"),
+                "fix the pattern, or remove it from members",
+            );
+        }
+        dirs.extend(found.into_iter().map(|d| (d, true)));
+    }
+    // a package at the top is a member whether or not it is listed
+    if doc.contains_key("package") && !dirs.iter().any(|(dir, _)| normalize_member(dir) == ".") {
+        dirs.insert(0, (".".to_string(), false));
+    }
+    for (dir, globbed) in &dirs {
+        let dir = dir.as_str();
+        if !seen.insert(normalize_member(dir)) {
+            // cargo doesn't mind a pattern matching a listed directory
+            if !globbed {
+                findings.warning(
+                    format!("The member entry {dir:?} is listed more than once"),
+                    "remove the duplicate from members",
+                );
+            }
+            continue;
+        }
+        let manifest: PathBuf = [dir, "Cargo.toml"].iter().collect();
+        let contents = match std::fs::read_to_string(&manifest) {
+            Ok(contents) => contents,
+            Err(e) => {
+                findings.error(
+                    format!("Can't read {}: {e}", manifest.display()),
+                    format!(
+                        "create {} or remove {dir:?} from members",
+                        manifest.display()
+                    ),
+                );
+                continue;
+            }
+        };
+        let member = match encoding::without_bom(&contents).parse::<Document>() {
+            Ok(member) => member,
+            Err(e) => {
+                findings.error(
+                    format!("{} isn't valid TOML: {e}", manifest.display()),
+                    "fix the syntax error; cargo will report the same problem",
+                );
+                continue;
+            }
+        };
+        let Some(package) = member.get("package") else {
+            findings.error(
+                format!("{} has no [package] section", manifest.display()),
+                format!(
+                    "add a [package] section, or remove {dir:?} from members if it isn't a crate"
+                ),
+            );
+            continue;
+        };
+        let name = package
+            .get("name")
+            .and_then(Item::as_str)
+            .unwrap_or(dir)
+            .to_string();
+        match package.get("version") {
+            Some(version) => {
+                if let Some(version) = version.as_str() {
+                    styles.push((name, Style::Explicit(version.to_string())));
+                } else if is_inherited(version) {
+                    if workspace_version.is_none() {
+                        findings.error(
+                            format!(
+                                "{} inherits its version, but [workspace.package] has no version",
+                                manifest.display()
+                            ),
+                            "add version = \"...\" to [workspace.package] in the top level Cargo.toml",
+                        );
+                    }
+                    styles.push((name, Style::Inherited));
+                } else {
+                    findings.error(
+                        format!("The version in {} isn't a string", manifest.display()),
+                        "write the version as version = \"x.y.z\" or version.workspace = true",
+                    );
+                }
+            }
+            None => findings.error(
+                format!("{} has no version", manifest.display()),
+                "add version = \"x.y.z\" or version.workspace = true to [package]",
+            ),
+        }
+    }
+
+    let inherited = styles
+        .iter()
+        .filter(|(_, s)| matches!(s, Style::Inherited))
+        .count();
+    if inherited > 0 && inherited < styles.len() {
+        let explicit = styles
+            .iter()
+            .filter(|(_, s)| matches!(s, Style::Explicit(_)))
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        findings.warning(
+            format!(
+                "Members mix inherited and explicit versions; these set their own: {}",
+                explicit.join(", ")
+            ),
+            "use version.workspace = true in every member so there is only one version to change",
+        );
+    }
+
+    lockfile_drift(&mut findings, &styles, workspace_version);
+    findings
+}
+
+/// Compare the versions in Cargo.lock to the manifests
+///
+/// cargo rewrites the lockfile on the next build, so drift isn't fatal,
+/// but it means a release commit doesn't contain what will be built
+fn lockfile_drift(
+    findings: &mut Findings,
+    styles: &[(String, Style)],
+    workspace_version: Option<&str>,
+) {
+    let Ok(lock) = std::fs::read_to_string("Cargo.lock") else {
+        return;
+    };
+    let Ok(lock) = lock.parse::<Document>() else {
+        findings.error(
+            "Cargo.lock isn't valid TOML",
+            "delete it and let cargo generate a new one",
+        );
+        return;
+    };
+    // packages from this workspace have no source in the lockfile
+    let locked = lock
+        .get("package")
+        .and_then(Item::as_array_of_tables)
+        .into_iter()
+        .flatten()
+        .filter(|p| !p.contains_key("source"))
+        .filter_map(|p| Some((p.get("name")?.as_str()?, p.get("version")?.as_str()?)))
+        .collect::<HashMap<_, _>>();
+
+    for (name, style) in styles {
+        let wanted = match style {
+            Style::Explicit(v) => Some(v.as_str()),
+            Style::Inherited => workspace_version,
+        };
+        if let (Some(wanted), Some(locked)) = (wanted, locked.get(name.as_str())) {
+            if wanted != *locked {
+                findings.warning(
+                    format!("Cargo.lock has {name} at {locked} but its manifest says {wanted}"),
+                    "run cargo update --workspace to refresh the lockfile",
+                );
+            }
+        }
+    }
+}
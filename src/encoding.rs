@@ -0,0 +1,102 @@
+//! Keeping the way a file was saved when we write it back: its line
+//! endings, whether it ends with a newline and whether it starts with a
+//! byte order mark, so a checkout with CRLF manifests or manifests
+//! saved by Windows editors only sees the versions change
+use std::{fs, path::Path};
+
+/// The byte order mark, as some Windows editors start UTF-8 files with
+const BOM: char = '\u{feff}';
+
+/// The new contents of a file, written the way the file already is
+///
+/// A file that can't be read, like one that doesn't exist yet, gets
+/// the contents as they are
+///
+/// * `path` - the file about to be written
+/// * `contents` - what it should contain, with any line endings
+pub fn like_file(path: &Path, contents: String) -> String {
+    match fs::read_to_string(path) {
+        Ok(original) => like(&original, contents),
+        Err(_) => contents,
+    }
+}
+
+/// The new contents written the way the original was
+///
+/// Windows line endings are used if most of the original's lines have
+/// them, the final newline is added or removed to match, and so is the
+/// byte order mark
+///
+/// * `original` - what the file contains now
+/// * `contents` - what it should contain, with any line endings
+pub fn like(original: &str, contents: String) -> String {
+    let crlf = original.matches("\r\n").count();
+    let lf = original.matches('\n').count() - crlf;
+    let mut contents = contents.replace("\r\n", "\n");
+    if !original.is_empty() {
+        if original.ends_with('\n') {
+            if !contents.is_empty() && !contents.ends_with('\n') {
+                contents.push('\n');
+            }
+        } else {
+            while contents.ends_with('\n') {
+                contents.pop();
+            }
+        }
+    }
+    if crlf > lf {
+        contents = contents.replace('\n', "\r\n");
+    }
+    if original.starts_with(BOM) && !contents.starts_with(BOM) {
+        contents.insert(0, BOM);
+    }
+    contents
+}
+
+/// The text without the byte order mark it may start with, which the
+/// TOML parser doesn't accept
+///
+/// * `text` - the contents of a file
+pub fn without_bom(text: &str) -> &str {
+    text.strip_prefix(BOM).unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crlf_and_bom_round_trip() {
+        let original = "\u{feff}[package]\r\nname = \"a\"\r\nversion = \"1.0.0\" # keep\r\n";
+        let mut doc = without_bom(original)
+            .parse::<toml_edit::Document>()
+            .unwrap();
+        let version = doc["package"]["version"].as_value_mut().unwrap();
+        let decor = version.decor().clone();
+        *version = "2.0.0".into();
+        *version.decor_mut() = decor;
+        // whatever line endings toml_edit writes, the file gets CRLF back
+        let written = like(original, doc.to_string());
+        assert_eq!(
+            written.as_bytes(),
+            "\u{feff}[package]\r\nname = \"a\"\r\nversion = \"2.0.0\" # keep\r\n".as_bytes()
+        );
+        // nothing changes when the document is written back as it was
+        let doc = without_bom(original)
+            .parse::<toml_edit::Document>()
+            .unwrap();
+        assert_eq!(
+            like(original, doc.to_string()).as_bytes(),
+            original.as_bytes()
+        );
+    }
+
+    #[test]
+    fn final_newline() {
+        assert_eq!(like("a\nb", "a\nc\n".to_string()), "a\nc");
+        assert_eq!(like("a\nb\n", "a\nc".to_string()), "a\nc\n");
+        assert_eq!(like("a\r\nb\r\n", "a\r\nc".to_string()), "a\r\nc\r\n");
+        assert_eq!(like("", "a\n".to_string()), "a\n");
+        assert_eq!(like("\u{feff}", "a".to_string()), "\u{feff}a");
+    }
+}
@@ -0,0 +1,490 @@
+//! Handlers for files outside of cargo that carry the workspace version,
+//! such as packaging scripts for other distribution channels
+use std::{ops::Range, path::PathBuf};
+
+use anyhow::{anyhow, bail, Error};
+use toml_edit::{Document, Formatted, Item, Value};
+
+use crate::config::Config;
+
+/// The kind of an extra file, which determines how we find the version
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// An Arch Linux PKGBUILD, where `pkgver=` holds the version
+    Pkgbuild,
+    /// A WiX source file or the `wix.toml` of cargo-wix, where the
+    /// installer's `ProductVersion` lives
+    Wix,
+    /// A Maven `pom.xml`, where the project's `<version>` holds the version
+    Maven,
+    /// A `gradle.properties`, where `version=` holds the version
+    Gradle,
+}
+
+impl Kind {
+    /// The setting in the configuration that lists files of this kind
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            Kind::Pkgbuild => "pkgbuild",
+            Kind::Wix => "wix",
+            Kind::Maven => "maven",
+            Kind::Gradle => "gradle",
+        }
+    }
+}
+
+/// A file listed in the configuration that should carry the version
+#[derive(Debug)]
+pub struct ExtraFile {
+    /// how to find the version inside this file
+    pub kind: Kind,
+    /// where the file is, relative to the workspace root
+    pub path: PathBuf,
+}
+
+/// A single value inside an extra file that needs to change
+#[derive(Debug)]
+pub struct Edit {
+    /// what was changed, such as `pkgver`
+    pub what: String,
+    /// the value found in the file
+    pub old: String,
+    /// the value we want instead
+    pub new: String,
+}
+
+impl ExtraFile {
+    /// List all the extra files mentioned in the configuration
+    pub fn from_config(config: &Config) -> Vec<ExtraFile> {
+        let files = |kind, paths: &Vec<PathBuf>| {
+            paths
+                .iter()
+                .map(|path| ExtraFile {
+                    kind,
+                    path: path.clone(),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut all = files(Kind::Pkgbuild, &config.pkgbuild);
+        all.extend(files(Kind::Wix, &config.wix));
+        all.extend(files(Kind::Maven, &config.maven));
+        all.extend(files(Kind::Gradle, &config.gradle));
+        all
+    }
+
+    /// Compute the contents of this file with the new version applied
+    ///
+    /// * `contents` - the current contents of the file
+    /// * `version` - the version we want, without any leading 'v'
+    ///
+    /// Returns the new contents and the list of edits made; the list
+    /// is empty if the file already had the right version
+    pub fn rewrite(&self, contents: &str, version: &str) -> Result<(String, Vec<Edit>), Error> {
+        match self.kind {
+            Kind::Pkgbuild => pkgbuild(contents, version)
+                .ok_or(anyhow!("no pkgver= line in {}", self.path.display())),
+            Kind::Wix => {
+                let version = msi_version(version)?;
+                if self.path.extension().is_some_and(|ext| ext == "toml") {
+                    wix_toml(contents, &version)
+                } else {
+                    wxs(contents, &version)
+                        .ok_or(anyhow!("no ProductVersion in {}", self.path.display()))
+                }
+            }
+            Kind::Maven => pom(contents, version).ok_or(anyhow!(
+                "no <version> in the <project> of {}",
+                self.path.display()
+            )),
+            Kind::Gradle => gradle(contents, version)
+                .ok_or(anyhow!("no version= line in {}", self.path.display())),
+        }
+    }
+}
+
+/// Update `pkgver=` in a PKGBUILD
+///
+/// makepkg doesn't allow hyphens in pkgver, so a pre-release like
+/// `1.0.0-rc.1` is written as `1.0.0_rc.1`. When the pkgver changes
+/// the package release number is no longer meaningful, so `pkgrel=`
+/// is reset to 1
+///
+/// Returns `None` if there is no `pkgver=` line at all
+fn pkgbuild(contents: &str, version: &str) -> Option<(String, Vec<Edit>)> {
+    let pkgver = version.replace('-', "_");
+    let mut lines = contents
+        .split_inclusive('\n')
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let mut edits = vec![];
+
+    let pos = lines.iter().position(|l| is_assignment(l, "pkgver"))?;
+    if let Some(edit) = replace_assignment(&mut lines[pos], &pkgver) {
+        edits.push(edit);
+        if let Some(pos) = lines.iter().position(|l| is_assignment(l, "pkgrel")) {
+            edits.extend(replace_assignment(&mut lines[pos], "1"));
+        }
+    }
+    Some((lines.concat(), edits))
+}
+
+/// Update `version=` in a `gradle.properties`
+///
+/// Returns `None` if there is no `version=` line at all
+fn gradle(contents: &str, version: &str) -> Option<(String, Vec<Edit>)> {
+    let mut lines = contents
+        .split_inclusive('\n')
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let pos = lines.iter().position(|l| is_assignment(l, "version"))?;
+    let edits = replace_assignment(&mut lines[pos], version)
+        .into_iter()
+        .collect();
+    Some((lines.concat(), edits))
+}
+
+/// Check if a line is a `name=value` assignment for the given name
+fn is_assignment(line: &str, name: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(name)
+        .is_some_and(|rest| rest.trim_start().starts_with('='))
+}
+
+/// Replace the value of a `name=value` line
+///
+/// Any quoting around the value, the indentation, whatever follows the
+/// value like a `# comment`, and the line ending are kept as they were
+///
+/// * `line` - the line to change, which must be an assignment
+/// * `value` - the new value
+///
+/// Returns the edit if the value was different
+fn replace_assignment(line: &mut String, value: &str) -> Option<Edit> {
+    let (name, rest) = line.split_once('=')?;
+    let body = rest.trim_end_matches(['\r', '\n']);
+    let eol = &rest[body.len()..];
+    let leading = &body[..body.len() - body.trim_start().len()];
+    let body = body.trim_start();
+    // a quoted value ends at the closing quote, and a bare one at the
+    // first space or comment
+    let (quote, old, tail) = match body.chars().next() {
+        Some(q @ ('"' | '\'')) => {
+            let inner = &body[1..];
+            match inner.find(q) {
+                Some(end) => (q.to_string(), &inner[..end], &inner[end + 1..]),
+                None => (q.to_string(), inner.trim_end(), ""),
+            }
+        }
+        _ => {
+            let end = body
+                .find(|c: char| c.is_whitespace() || c == '#')
+                .unwrap_or(body.len());
+            (String::new(), &body[..end], &body[end..])
+        }
+    };
+    if old == value {
+        return None;
+    }
+    let edit = Edit {
+        what: name.trim().to_string(),
+        old: old.to_string(),
+        new: value.to_string(),
+    };
+    *line = format!("{name}={leading}{quote}{value}{quote}{tail}{eol}");
+    Some(edit)
+}
+
+/// Convert a version into the form Windows Installer requires
+///
+/// MSI product versions are `major.minor.build`, where major and minor
+/// must be less than 256 and build less than 65536. Any pre-release or
+/// build metadata is dropped, since MSI has nowhere to put it
+fn msi_version(version: &str) -> Result<String, Error> {
+    let core = version.split(['-', '+']).next().unwrap_or_default();
+    let parts = core
+        .split('.')
+        .map(|part| part.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| anyhow!("{version} can't be used as an MSI ProductVersion"))?;
+    let [major, minor, build] = parts[..] else {
+        bail!("{version} must have exactly three components to be an MSI ProductVersion")
+    };
+    if major > 255 || minor > 255 || build > 65535 {
+        bail!("{version} is out of range for an MSI ProductVersion (max 255.255.65535)")
+    }
+    Ok(format!("{major}.{minor}.{build}"))
+}
+
+/// Update the product version in a WiX source file
+///
+/// The version is looked for first in a `<?define ProductVersion = "..." ?>`
+/// preprocessor variable, and failing that in the `Version` attribute of
+/// the `<Product>` (WiX 3) or `<Package>` (WiX 4) element. An attribute
+/// that refers to a variable such as `$(var.Version)` is left alone
+///
+/// Returns `None` if neither could be found
+fn wxs(contents: &str, version: &str) -> Option<(String, Vec<Edit>)> {
+    let range = define_value(contents, "ProductVersion").or_else(|| {
+        ["<Product", "<Package"]
+            .iter()
+            .find_map(|element| element_attribute(contents, element, "Version"))
+            .filter(|range| !contents[range.clone()].starts_with("$("))
+    })?;
+    let old = &contents[range.clone()];
+    if old == version {
+        return Some((contents.to_string(), vec![]));
+    }
+    let edit = Edit {
+        what: "ProductVersion".to_string(),
+        old: old.to_string(),
+        new: version.to_string(),
+    };
+    let mut contents = contents.to_string();
+    contents.replace_range(range, version);
+    Some((contents, vec![edit]))
+}
+
+/// Find the value of a `<?define name = "value" ?>` preprocessor variable
+///
+/// Returns the byte range of the value, not including the quotes
+fn define_value(contents: &str, name: &str) -> Option<Range<usize>> {
+    contents.match_indices("<?define").find_map(|(start, _)| {
+        let start = start + "<?define".len();
+        let rest = &contents[start..];
+        let rest = rest.trim_start().strip_prefix(name)?;
+        let rest = rest.trim_start().strip_prefix('=')?;
+        quoted(contents, contents.len() - rest.trim_start().len())
+    })
+}
+
+/// Find the value of an attribute of the first element with the given name
+///
+/// * `element` - the start of the tag, such as `<Product`
+/// * `attribute` - the attribute name, such as `Version`
+///
+/// Returns the byte range of the value, not including the quotes
+fn element_attribute(contents: &str, element: &str, attribute: &str) -> Option<Range<usize>> {
+    let (start, _) = contents.match_indices(element).find(|(start, found)| {
+        contents[start + found.len()..].starts_with(|c: char| c.is_ascii_whitespace())
+    })?;
+    let end = start + contents[start..].find('>')?;
+    let tag = &contents[start..end];
+    // the attribute must be preceded by whitespace, so that looking
+    // for Version doesn't find InstallerVersion
+    let (offset, _) = tag.match_indices(attribute).find(|(offset, _)| {
+        tag[..*offset].ends_with(|c: char| c.is_ascii_whitespace())
+            && tag[offset + attribute.len()..]
+                .trim_start()
+                .starts_with('=')
+    })?;
+    let rest = tag[offset + attribute.len()..]
+        .trim_start()
+        .strip_prefix('=')?;
+    quoted(contents, end - rest.trim_start().len())
+}
+
+/// Return the range of the quoted string starting at `start`
+///
+/// Either single or double quotes are accepted
+fn quoted(contents: &str, start: usize) -> Option<Range<usize>> {
+    let quote = contents[start..]
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '"' | '\''))?;
+    let len = contents[start + 1..].find(quote)?;
+    Some(start + 1..start + 1 + len)
+}
+
+/// Update the `version` key at the top level of a cargo-wix `wix.toml`
+fn wix_toml(contents: &str, version: &str) -> Result<(String, Vec<Edit>), Error> {
+    let mut doc = contents.parse::<Document>()?;
+    let mut edits = vec![];
+    match doc.get_mut("version") {
+        Some(Item::Value(v)) => {
+            let old = v
+                .as_str()
+                .ok_or(anyhow!("version in wix.toml wasn't a string"))?;
+            if old != version {
+                edits.push(Edit {
+                    what: "ProductVersion".to_string(),
+                    old: old.to_string(),
+                    new: version.to_string(),
+                });
+                // keep any comments or spacing around the value
+                let decor = v.decor().clone();
+                *v = Value::String(Formatted::new(version.to_string()));
+                *v.decor_mut() = decor;
+            }
+        }
+        _ => bail!("no version in wix.toml"),
+    }
+    Ok((doc.to_string(), edits))
+}
+
+/// Update the project version in a Maven `pom.xml`
+///
+/// Only a `<version>` that is a direct child of `<project>` is changed;
+/// the ones inside `<parent>`, `<dependencies>` or `<plugins>` belong
+/// to other artifacts
+///
+/// Returns `None` if the project has no `<version>`
+fn pom(contents: &str, version: &str) -> Option<(String, Vec<Edit>)> {
+    let range = project_version(contents)?;
+    // leave any whitespace around the version where it was
+    let text = &contents[range.clone()];
+    let start = range.start + text.len() - text.trim_start().len();
+    let range = start..start + text.trim().len();
+    let old = &contents[range.clone()];
+    if old == version {
+        return Some((contents.to_string(), vec![]));
+    }
+    let edit = Edit {
+        what: "<version>".to_string(),
+        old: old.to_string(),
+        new: version.to_string(),
+    };
+    let mut contents = contents.to_string();
+    contents.replace_range(range, version);
+    Some((contents, vec![edit]))
+}
+
+/// Find the text of `<project><version>` in a pom.xml
+///
+/// This is a very small XML scanner that only tracks which element we
+/// are inside of; comments, processing instructions and declarations
+/// are skipped
+///
+/// Returns the byte range of the text inside the `<version>` element
+fn project_version(contents: &str) -> Option<Range<usize>> {
+    let mut stack: Vec<&str> = vec![];
+    let mut pos = 0;
+    while let Some(offset) = contents[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &contents[start..];
+        let terminator = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<![CDATA[") {
+            "]]>"
+        } else {
+            ">"
+        };
+        let end = start + rest.find(terminator)? + terminator.len();
+        let tag = &contents[start + 1..end - 1];
+        pos = end;
+        if tag.starts_with(['?', '!']) {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            // tolerate mismatched tags by popping back to the matching one
+            if let Some(depth) = stack.iter().rposition(|open| *open == name.trim()) {
+                stack.truncate(depth);
+            }
+            continue;
+        }
+        if tag.ends_with('/') {
+            continue;
+        }
+        let name = tag.split_ascii_whitespace().next().unwrap_or_default();
+        if name == "version" && stack == ["project"] {
+            let len = contents[end..].find("</version")?;
+            return Some(end..end + len);
+        }
+        stack.push(name);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assignments() {
+        let cases = [
+            ("pkgver=1.0.0\n", "pkgver=2.0.0\n"),
+            ("pkgver=\"1.0.0\"\n", "pkgver=\"2.0.0\"\n"),
+            ("pkgver='1.0.0'\r\n", "pkgver='2.0.0'\r\n"),
+            (
+                "pkgver=1.0.0 # keep in sync\n",
+                "pkgver=2.0.0 # keep in sync\n",
+            ),
+            ("pkgver=1.0.0# keep\n", "pkgver=2.0.0# keep\n"),
+            ("pkgver=\"1.0.0\" # x\n", "pkgver=\"2.0.0\" # x\n"),
+            ("  pkgver = 1.0.0\n", "  pkgver = 2.0.0\n"),
+        ];
+        for (line, want) in cases {
+            let mut changed = line.to_string();
+            let edit = replace_assignment(&mut changed, "2.0.0").unwrap();
+            assert_eq!(changed, want);
+            assert_eq!(edit.old, "1.0.0", "{line:?}");
+        }
+    }
+
+    #[test]
+    fn assignment_unchanged() {
+        for line in ["pkgver=1.0.0 # keep in sync\n", "pkgver=\"1.0.0\" # x"] {
+            let mut same = line.to_string();
+            assert!(replace_assignment(&mut same, "1.0.0").is_none(), "{line:?}");
+            assert_eq!(same, line);
+        }
+    }
+
+    #[test]
+    fn pkgbuild_resets_pkgrel() {
+        let contents = "pkgname=foo\npkgver=1.0.0 # sync\npkgrel=3\n";
+        let (new, edits) = pkgbuild(contents, "1.1.0-rc.1").unwrap();
+        assert_eq!(new, "pkgname=foo\npkgver=1.1.0_rc.1 # sync\npkgrel=1\n");
+        assert_eq!(edits.len(), 2);
+        // the release number stays when the version is already right
+        let contents = "pkgver=1.0.0\npkgrel=3\n";
+        assert_eq!(pkgbuild(contents, "1.0.0").unwrap().0, contents);
+        assert!(pkgbuild("pkgname=foo\n", "1.0.0").is_none());
+    }
+
+    #[test]
+    fn msi_versions() {
+        let cases = [
+            ("1.2.3", Some("1.2.3")),
+            ("1.2.3-rc.1+b", Some("1.2.3")),
+            ("255.255.65535", Some("255.255.65535")),
+            ("256.0.0", None),
+            ("0.256.0", None),
+            ("0.0.65536", None),
+            ("1.2", None),
+            ("1.2.3.4", None),
+            ("1.x.3", None),
+        ];
+        for (version, want) in cases {
+            assert_eq!(msi_version(version).ok().as_deref(), want, "{version}");
+        }
+    }
+
+    #[test]
+    fn wxs_versions() {
+        let define =
+            "<?define ProductVersion = \"1.0.0\" ?>\n<Product Version=\"$(var.ProductVersion)\">";
+        let (new, edits) = wxs(define, "1.2.3").unwrap();
+        assert_eq!(
+            new,
+            "<?define ProductVersion = \"1.2.3\" ?>\n<Product Version=\"$(var.ProductVersion)\">"
+        );
+        assert_eq!(edits[0].old, "1.0.0");
+        let package = "<Package Name='x' InstallerVersion='500' Version='1.0.0'>";
+        let (new, _) = wxs(package, "1.2.3").unwrap();
+        assert_eq!(
+            new,
+            "<Package Name='x' InstallerVersion='500' Version='1.2.3'>"
+        );
+        assert!(wxs(package, "1.0.0").unwrap().1.is_empty());
+        assert!(wxs("<Product Version=\"$(var.V)\">", "1.2.3").is_none());
+    }
+
+    #[test]
+    fn wix_toml_version() {
+        let contents = "version = \"1.0.0\" # the installer\nname = \"x\"\n";
+        let (new, edits) = wix_toml(contents, "1.2.3").unwrap();
+        assert_eq!(new, "version = \"1.2.3\" # the installer\nname = \"x\"\n");
+        assert_eq!(edits.len(), 1);
+        assert!(wix_toml("name = \"x\"\n", "1.2.3").is_err());
+    }
+}
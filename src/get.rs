@@ -0,0 +1,49 @@
+//! The `get` subcommand: print the current workspace version, for
+//! scripts
+use anyhow::{anyhow, Error};
+use toml_edit::Item;
+
+use crate::{
+    json::Json,
+    workspace::{DeclaredVersion, Workspace},
+    Format,
+};
+
+/// Print the workspace version, and where it came from for JSON
+///
+/// This is the same version everything else works from: the one in
+/// `[workspace.package]`, then the root package, then the first member
+/// that declares one
+///
+/// * `ws` - the workspace
+/// * `format` - the version alone for scripts, or JSON for tooling
+pub fn print(ws: &Workspace, format: Format) -> Result<(), Error> {
+    let root = ws
+        .doc
+        .get("package")
+        .and_then(|p| p.get("version"))
+        .and_then(Item::as_str);
+    let (version, source) = if let Some(version) = ws.package_version() {
+        (version, "[workspace.package]".to_string())
+    } else if let Some(version) = root {
+        (version, format!("[package] in {}", ws.manifest.display()))
+    } else {
+        ws.members
+            .iter()
+            .find_map(|m| match m.declared_version() {
+                DeclaredVersion::Version(v) => Some((v, m.manifest.display().to_string())),
+                _ => None,
+            })
+            .ok_or(anyhow!("The workspace has no version"))?
+    };
+    match format {
+        Format::Text => println!("{version}"),
+        Format::Json => println!(
+            "{:#}",
+            Json::object()
+                .with("version", version)
+                .with("source", source)
+        ),
+    }
+    Ok(())
+}
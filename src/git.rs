@@ -0,0 +1,120 @@
+//! Asking git about the repository the workspace lives in
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::{config::Config, version::Version};
+
+/// Run git with the given arguments and return what it printed
+///
+/// Fails if git can't be started or exits with an error, in which case
+/// the error includes whatever git wrote to stderr
+pub fn git(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Can't run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The most recent tag reachable from HEAD, if there is one
+pub fn latest_tag() -> Option<String> {
+    git(&["describe", "--tags", "--abbrev=0"]).ok()
+}
+
+/// The abbreviated hash of the commit that is checked out
+pub fn short_sha() -> Result<String, Error> {
+    git(&["rev-parse", "--short", "HEAD"])
+}
+
+/// Every tag reachable from HEAD, so not those on other branches
+///
+/// Returns an empty list if this isn't a git repository
+pub fn merged_tags() -> Vec<String> {
+    git(&["tag", "--list", "--merged", "HEAD"])
+        .map(|tags| tags.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// The newest release tag reachable from HEAD, and its version
+///
+/// Release tags are those `tag-prefix` says are for the workspace
+///
+/// * `config` - the settings, for the tag prefix
+/// * `stable` - leave out tags for pre-releases
+pub fn latest_release_tag(config: &Config, stable: bool) -> Option<(String, Version)> {
+    merged_tags()
+        .into_iter()
+        .filter_map(|tag| {
+            let version = config.tag_version(&tag)?.parse::<Version>().ok()?;
+            (!stable || version.pre.is_empty()).then_some((version, tag))
+        })
+        .max()
+        .map(|(version, tag)| (tag, version))
+}
+
+/// Every tag in the repository
+///
+/// Returns an empty list if this isn't a git repository
+pub fn tags() -> Vec<String> {
+    git(&["tag", "--list"])
+        .map(|tags| tags.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Find the commit a branch or tag of a remote repository points to
+///
+/// * `url` - the repository, as it would be given to `git clone`
+/// * `reference` - a branch or tag name, or `HEAD` for the default branch
+pub fn ls_remote(url: &str, reference: &str) -> Result<String, Error> {
+    let output = git(&["ls-remote", url, reference])?;
+    // each line is "<sha>\t<ref>"; a tag may also list its peeled commit
+    // as "<sha>\t<ref>^{}", which is the one we want if it is there
+    let refs = output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .collect::<Vec<_>>();
+    refs.iter()
+        .find(|(_, name)| name.ends_with("^{}"))
+        .or(refs.first())
+        .map(|(sha, _)| sha.to_string())
+        .ok_or(anyhow!("{reference} wasn't found in {url}"))
+}
+
+/// The URL of the `origin` remote, if there is one
+pub fn remote_url() -> Option<String> {
+    git(&["remote", "get-url", "origin"]).ok()
+}
+
+/// The branch being built or checked out, if there is one
+///
+/// CI usually checks out a detached HEAD, so the branch it names in
+/// the environment comes first: the source branch of a pull request or
+/// merge request, then the branch of the push
+pub fn current_branch() -> Option<String> {
+    let from_ci = [
+        "GITHUB_HEAD_REF",
+        "CI_MERGE_REQUEST_SOURCE_BRANCH_NAME",
+        "CI_COMMIT_BRANCH",
+    ]
+    .iter()
+    .filter_map(|name| std::env::var(name).ok())
+    .chain(
+        std::env::var("GITHUB_REF")
+            .ok()
+            .and_then(|r| r.strip_prefix("refs/heads/").map(String::from)),
+    )
+    .find(|branch| !branch.is_empty());
+    from_ci.or_else(|| {
+        git(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .ok()
+            .filter(|branch| !branch.is_empty())
+    })
+}
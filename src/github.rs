@@ -0,0 +1,302 @@
+//! Reporting results to GitHub through its REST API, and to the
+//! workflow running us in GitHub Actions
+//!
+//! Everything needed is taken from the environment GitHub Actions
+//! sets up, with the token in `GITHUB_TOKEN` or `GH_TOKEN`; that can be
+//! the workflow's own token or one for a GitHub App installation
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::{
+    git, http,
+    json::Json,
+    markdown::COMMENT_MARKER,
+    report::{self, Difference},
+};
+
+/// The name check runs are created under
+const CHECK_NAME: &str = "workspace-version";
+
+/// The most annotations GitHub accepts in a single request
+const MAX_ANNOTATIONS: usize = 50;
+
+/// How to reach the API for the repository being checked
+#[derive(Debug)]
+struct Api {
+    /// the base URL, which differs for GitHub Enterprise Server
+    url: String,
+    /// `owner/name` of the repository
+    repository: String,
+    /// the token to authenticate with
+    token: String,
+}
+
+impl Api {
+    /// Find the API, repository, and token from the environment
+    fn from_env() -> Result<Api, Error> {
+        let url =
+            std::env::var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com".into());
+        let repository = std::env::var("GITHUB_REPOSITORY")
+            .map_err(|_| anyhow!("GITHUB_REPOSITORY must be set to owner/name"))?;
+        let token = std::env::var("GITHUB_TOKEN")
+            .or_else(|_| std::env::var("GH_TOKEN"))
+            .map_err(|_| anyhow!("GITHUB_TOKEN or GH_TOKEN must be set"))?;
+        Ok(Api {
+            url,
+            repository,
+            token,
+        })
+    }
+
+    /// Send a request about the repository and return the response
+    ///
+    /// * `method` - POST, PATCH, and so on
+    /// * `path` - where to send it, after `/repos/owner/name/`
+    /// * `body` - the JSON to send, if any
+    fn send(&self, method: &str, path: &str, body: Option<&Json>) -> Result<Json, Error> {
+        let url = format!("{}/repos/{}/{path}", self.url, self.repository);
+        let auth = format!("Bearer {}", self.token);
+        let headers = [
+            ("Authorization", auth.as_str()),
+            ("Accept", "application/vnd.github+json"),
+            ("Content-Type", "application/json"),
+            ("X-GitHub-Api-Version", "2022-11-28"),
+        ];
+        let body = body.map(Json::to_string);
+        let response = http::request(method, &url, &headers, body.as_deref())?;
+        if !response.ok() {
+            bail!(
+                "GitHub returned {} for {method} {path}: {}",
+                response.status,
+                response.body.trim()
+            )
+        }
+        Json::parse(&response.body)
+    }
+}
+
+/// Create a completed check run with an annotation for each difference
+///
+/// GitHub only takes a limited number of annotations at once, so any
+/// beyond that are added by updating the check run afterwards
+///
+/// * `newver` - the version that was checked for
+/// * `differences` - everything that didn't have that version
+/// * `error` - why the check failed, if it did for some other reason
+/// * `quiet` - don't say where the check run is
+pub fn check_run(
+    newver: &str,
+    differences: &[Difference],
+    error: Option<&Error>,
+    quiet: bool,
+) -> Result<(), Error> {
+    let api = Api::from_env()?;
+    let head_sha = match std::env::var("GITHUB_SHA") {
+        Ok(sha) => sha,
+        Err(_) => git::git(&["rev-parse", "HEAD"])?,
+    };
+    // annotations need paths from the top of the repository, and the
+    // workspace might be in a subdirectory of it
+    let prefix = git::git(&["rev-parse", "--show-prefix"]).unwrap_or_default();
+
+    let failed = !differences.is_empty() || error.is_some();
+    let title = if differences.is_empty() && failed {
+        "The version check failed".to_string()
+    } else if failed {
+        format!("{} value(s) don't have version {newver}", differences.len())
+    } else {
+        format!("Everything has version {newver}")
+    };
+    let summary = match error {
+        Some(e) if differences.is_empty() => format!("{e:#}"),
+        None => "All files had the correct version".to_string(),
+        Some(_) => differences
+            .iter()
+            .map(|d| format!("* {}\n", d.message))
+            .collect::<String>(),
+    };
+    let annotations = differences
+        .iter()
+        .map(|d| {
+            Json::object()
+                .with(
+                    "path",
+                    format!("{prefix}{}", report::display_path(&d.change.path)),
+                )
+                .with("start_line", d.line)
+                .with("end_line", d.line)
+                .with("annotation_level", "failure")
+                .with("message", d.message.as_str())
+        })
+        .collect::<Vec<_>>();
+    let mut chunks = annotations.chunks(MAX_ANNOTATIONS);
+    let output = |annotations: Option<&[Json]>| {
+        Json::object()
+            .with("title", title.as_str())
+            .with("summary", summary.as_str())
+            .with("annotations", annotations.unwrap_or_default().to_vec())
+    };
+
+    let created = api.send(
+        "POST",
+        "check-runs",
+        Some(
+            &Json::object()
+                .with("name", CHECK_NAME)
+                .with("head_sha", head_sha)
+                .with("status", "completed")
+                .with("conclusion", if failed { "failure" } else { "success" })
+                .with("output", output(chunks.next())),
+        ),
+    )?;
+    let id = created
+        .get("id")
+        .and_then(Json::as_number)
+        .ok_or(anyhow!("GitHub didn't say which check run it created"))?;
+    for chunk in chunks {
+        api.send(
+            "PATCH",
+            &format!("check-runs/{id}"),
+            Some(&Json::object().with("output", output(Some(chunk)))),
+        )?;
+    }
+    if !quiet {
+        if let Some(url) = created.get("html_url").and_then(Json::as_str) {
+            println!("Created check run {url}");
+        }
+    }
+    Ok(())
+}
+
+/// Check if we are running in GitHub Actions
+pub fn in_actions() -> bool {
+    std::env::var("GITHUB_ACTIONS").as_deref() == Ok("true")
+}
+
+/// Workflow commands that annotate each difference, which GitHub
+/// Actions shows on the line in the pull request's diff
+///
+/// * `differences` - everything that didn't have the version
+pub fn workflow_annotations(differences: &[Difference]) -> String {
+    // as with check runs, the paths are from the top of the repository
+    let prefix = git::git(&["rev-parse", "--show-prefix"]).unwrap_or_default();
+    let escape = |text: &str| {
+        text.replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    differences
+        .iter()
+        .map(|d| {
+            let file = format!("{prefix}{}", report::display_path(&d.change.path));
+            format!(
+                "::error file={},line={},col={},title=Wrong version::{}\n",
+                escape(&file).replace(':', "%3A").replace(',', "%2C"),
+                d.line,
+                d.column,
+                escape(&d.message)
+            )
+        })
+        .collect()
+}
+
+/// Give later steps of the workflow the results of an update, if it is
+/// running in GitHub Actions
+///
+/// The outputs are appended to the file in `GITHUB_OUTPUT`:
+/// `new_version`, and `changed_files` and `changed_crates` as JSON
+/// lists, for `fromJSON` in the workflow
+///
+/// * `newver` - the version that was applied
+/// * `files` - every file that was changed
+/// * `crates` - the names of the crates whose version changed
+pub fn set_outputs(newver: &str, files: &[String], crates: &[&str]) -> Result<(), Error> {
+    let Some(path) = std::env::var_os("GITHUB_OUTPUT") else {
+        return Ok(());
+    };
+    let outputs = format!(
+        "new_version={newver}\nchanged_files={}\nchanged_crates={}\n",
+        Json::from(files.iter().map(String::as_str).collect::<Vec<_>>()),
+        Json::from(crates.to_vec())
+    );
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(outputs.as_bytes()))
+        .context("Can't write the outputs to GITHUB_OUTPUT")
+}
+
+/// Post a comment on the pull request being checked, or update the one
+/// posted by an earlier run
+///
+/// The comment is found again by a marker hidden in its body, so each
+/// pull request only ever has one
+///
+/// * `body` - the Markdown to post
+/// * `quiet` - don't say where the comment is
+pub fn pr_comment(body: &str, quiet: bool) -> Result<(), Error> {
+    let api = Api::from_env()?;
+    let number = pull_request()?;
+    let body = Json::object().with("body", format!("{COMMENT_MARKER}\n{body}"));
+
+    let mut existing = None;
+    for page in 1.. {
+        let comments = api.send(
+            "GET",
+            &format!("issues/{number}/comments?per_page=100&page={page}"),
+            None,
+        )?;
+        let comments = comments.as_array().unwrap_or_default();
+        existing = comments
+            .iter()
+            .find(|c| {
+                c.get("body")
+                    .and_then(Json::as_str)
+                    .is_some_and(|b| b.contains(COMMENT_MARKER))
+            })
+            .and_then(|c| c.get("id"))
+            .and_then(Json::as_number);
+        if existing.is_some() || comments.len() < 100 {
+            break;
+        }
+    }
+    let comment = match existing {
+        Some(id) => api.send("PATCH", &format!("issues/comments/{id}"), Some(&body))?,
+        None => api.send("POST", &format!("issues/{number}/comments"), Some(&body))?,
+    };
+    if !quiet {
+        if let Some(url) = comment.get("html_url").and_then(Json::as_str) {
+            println!("Commented on {url}");
+        }
+    }
+    Ok(())
+}
+
+/// The number of the pull request a workflow is running for
+///
+/// This is in `GITHUB_REF` as `refs/pull/<number>/merge` for
+/// `pull_request` events, and in the event payload for others, like
+/// `pull_request_target`
+fn pull_request() -> Result<u64, Error> {
+    let from_ref = std::env::var("GITHUB_REF").ok().and_then(|r| {
+        r.strip_prefix("refs/pull/")?
+            .split('/')
+            .next()?
+            .parse::<u64>()
+            .ok()
+    });
+    if let Some(number) = from_ref {
+        return Ok(number);
+    }
+    std::env::var("GITHUB_EVENT_PATH")
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|event| Json::parse(&event).ok())
+        .and_then(|event| event.get("pull_request")?.get("number")?.as_number())
+        .map(|n| n as u64)
+        .ok_or(anyhow!(
+            "Can't find the pull request; this only works in a workflow run for one"
+        ))
+}
@@ -0,0 +1,106 @@
+//! Reporting results to GitLab through its REST API
+//!
+//! The API and project are taken from the environment GitLab CI sets
+//! up. Job tokens can't write notes, so the token has to be a project
+//! or personal access token in `GITLAB_TOKEN`
+use anyhow::{anyhow, bail, Error};
+
+use crate::{http, json::Json, markdown::COMMENT_MARKER};
+
+/// How to reach the API for the project being checked
+#[derive(Debug)]
+struct Api {
+    /// the base URL of the v4 API
+    url: String,
+    /// the numeric ID of the project
+    project: String,
+    /// the token to authenticate with
+    token: String,
+}
+
+impl Api {
+    /// Find the API, project, and token from the environment
+    fn from_env() -> Result<Api, Error> {
+        let url = std::env::var("CI_API_V4_URL")
+            .map_err(|_| anyhow!("CI_API_V4_URL must be set to the GitLab API"))?;
+        let project = std::env::var("CI_PROJECT_ID")
+            .map_err(|_| anyhow!("CI_PROJECT_ID must be set to the project"))?;
+        let token = std::env::var("GITLAB_TOKEN")
+            .map_err(|_| anyhow!("GITLAB_TOKEN must be set to an access token"))?;
+        Ok(Api {
+            url,
+            project,
+            token,
+        })
+    }
+
+    /// Send a request about the project and return the response
+    ///
+    /// * `method` - GET, POST, and so on
+    /// * `path` - where to send it, after `/projects/<id>/`
+    /// * `body` - the JSON to send, if any
+    fn send(&self, method: &str, path: &str, body: Option<&Json>) -> Result<Json, Error> {
+        let url = format!("{}/projects/{}/{path}", self.url, self.project);
+        let headers = [
+            ("PRIVATE-TOKEN", self.token.as_str()),
+            ("Content-Type", "application/json"),
+        ];
+        let body = body.map(Json::to_string);
+        let response = http::request(method, &url, &headers, body.as_deref())?;
+        if !response.ok() {
+            bail!(
+                "GitLab returned {} for {method} {path}: {}",
+                response.status,
+                response.body.trim()
+            )
+        }
+        Json::parse(&response.body)
+    }
+}
+
+/// Post a note on the merge request being checked, or update the one
+/// posted by an earlier run
+///
+/// * `body` - the Markdown to post
+/// * `quiet` - don't say what was done
+pub fn mr_comment(body: &str, quiet: bool) -> Result<(), Error> {
+    let api = Api::from_env()?;
+    let iid = std::env::var("CI_MERGE_REQUEST_IID").map_err(|_| {
+        anyhow!("Can't find the merge request; this only works in a merge request pipeline")
+    })?;
+    let body = Json::object().with("body", format!("{COMMENT_MARKER}\n{body}"));
+
+    let mut existing = None;
+    for page in 1.. {
+        let notes = api.send(
+            "GET",
+            &format!("merge_requests/{iid}/notes?per_page=100&page={page}"),
+            None,
+        )?;
+        let notes = notes.as_array().unwrap_or_default();
+        existing = notes
+            .iter()
+            .find(|n| {
+                n.get("body")
+                    .and_then(Json::as_str)
+                    .is_some_and(|b| b.contains(COMMENT_MARKER))
+            })
+            .and_then(|n| n.get("id"))
+            .and_then(Json::as_number);
+        if existing.is_some() || notes.len() < 100 {
+            break;
+        }
+    }
+    match existing {
+        Some(id) => api.send(
+            "PUT",
+            &format!("merge_requests/{iid}/notes/{id}"),
+            Some(&body),
+        )?,
+        None => api.send("POST", &format!("merge_requests/{iid}/notes"), Some(&body))?,
+    };
+    if !quiet {
+        println!("Commented on merge request !{iid}");
+    }
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! Running the commands configured to run around an update
+use std::process::Command;
+
+use anyhow::{bail, Context, Error};
+
+/// What a hook is told about the update, through its environment
+#[derive(Debug)]
+pub struct Env<'a> {
+    /// the version before the update, if there was one
+    pub old: Option<&'a str>,
+    /// the version being updated to
+    pub new: &'a str,
+    /// whether nothing is actually being written
+    pub dry_run: bool,
+    /// the files that were changed, which is empty before the update
+    pub changed: Vec<String>,
+}
+
+/// Run a hook command, failing if it does
+///
+/// The command gets `WSV_OLD_VERSION`, `WSV_NEW_VERSION`, `WSV_DRY_RUN`
+/// (`1` or `0`), and `WSV_CHANGED_FILES` (one per line) in its
+/// environment, and also `PREV_VERSION`, `NEW_VERSION`, and `DRY_RUN`
+/// as cargo-release sets them, so hooks brought over from it still work
+///
+/// * `key` - the setting the command came from, for messages
+/// * `command` - the program followed by its arguments; nothing is run
+///   if this is empty
+/// * `env` - what to tell the command about the update
+/// * `quiet` - don't say what is being run
+pub fn run(key: &str, command: &[String], env: &Env<'_>, quiet: bool) -> Result<(), Error> {
+    let Some((program, args)) = command.split_first() else {
+        return Ok(());
+    };
+    if !quiet {
+        println!("Running {key}: {}", command.join(" "));
+    }
+    let old = env.old.unwrap_or_default();
+    let status = Command::new(program)
+        .args(args)
+        .env("WSV_OLD_VERSION", old)
+        .env("WSV_NEW_VERSION", env.new)
+        .env("WSV_DRY_RUN", if env.dry_run { "1" } else { "0" })
+        .env("WSV_CHANGED_FILES", env.changed.join("\n"))
+        .env("PREV_VERSION", old)
+        .env("NEW_VERSION", env.new)
+        .env("DRY_RUN", env.dry_run.to_string())
+        .status()
+        .context(format!("Can't run {key} {program}"))?;
+    if !status.success() {
+        bail!("{key} failed ({status})")
+    }
+    Ok(())
+}
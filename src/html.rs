@@ -0,0 +1,116 @@
+//! A standalone HTML page with the results of `check` or `update`, for
+//! CI to publish for people who don't read the logs
+use std::fmt::Write;
+
+use anyhow::Error;
+
+use crate::{
+    report::{self, Difference},
+    workspace::Workspace,
+};
+
+/// The styles for the page, kept inline so it is a single file
+const STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+th { background: #f3f3f3; }
+.ok { color: #1a7f37; }
+.bad { color: #cf222e; }
+pre { background: #f6f8fa; padding: 0.8em; overflow-x: auto; }
+.hunk { color: #6e7781; }
+.del { color: #cf222e; }
+.add { color: #1a7f37; }
+";
+
+/// Render the results as an HTML page
+///
+/// * `ws` - the workspace, as it was left by the update
+/// * `newver` - the version that was checked for or applied
+/// * `writing` - whether the values were changed, or only checked
+/// * `differences` - everything that didn't have the version
+/// * `error` - why the run failed, if it did for some other reason
+pub fn render(
+    ws: &Workspace,
+    newver: &str,
+    writing: bool,
+    differences: &[Difference],
+    error: Option<&Error>,
+) -> String {
+    let title = report::title(newver, writing);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n<h1>{}</h1>",
+        escape(&title),
+        escape(&title)
+    );
+
+    out.push_str("<h2>Summary</h2>\n");
+    let summary = report::summary(newver, writing, differences);
+    let class = if differences.is_empty() || writing {
+        "ok"
+    } else {
+        "bad"
+    };
+    let _ = writeln!(out, "<p class=\"{class}\">{}</p>", escape(&summary));
+    if let Some(e) = error.filter(|e| !e.is::<report::DifferencesFound>()) {
+        let _ = writeln!(out, "<p class=\"bad\">{}</p>", escape(&format!("{e:#}")));
+    }
+
+    out.push_str(
+        "<h2>Crates</h2>\n<table>\n<tr><th>Crate</th><th>Version</th><th>Differences</th></tr>\n",
+    );
+    for c in report::crates(ws, differences) {
+        let new = c.new.unwrap_or("none");
+        let version = match &c.old {
+            Some(old) => format!("{old} \u{2192} {new}"),
+            None => new.to_string(),
+        };
+        let class = if c.differences == 0 { "ok" } else { "bad" };
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td class=\"{class}\">{}</td></tr>",
+            escape(c.name),
+            escape(&version),
+            c.differences
+        );
+    }
+    out.push_str("</table>\n");
+
+    if !differences.is_empty() {
+        out.push_str("<h2>Changes</h2>\n");
+    }
+    for path in report::files(differences) {
+        let _ = writeln!(
+            out,
+            "<h3>{}</h3>\n<pre>",
+            escape(&report::display_path(path))
+        );
+        for d in differences.iter().filter(|d| d.change.path == path) {
+            let what = d.change.toml_path.as_deref().unwrap_or(&d.change.key);
+            let _ = writeln!(
+                out,
+                "<span class=\"hunk\">@@ line {} {} @@</span>\n\
+                 <span class=\"del\">-{}</span>\n<span class=\"add\">+{}</span>",
+                d.line,
+                escape(what),
+                escape(&d.change.old),
+                escape(&d.change.new)
+            );
+        }
+        out.push_str("</pre>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Escape text so it can go in HTML, inside elements or attributes
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,118 @@
+//! Talking to web APIs
+//!
+//! Requests are made by running curl, which is available nearly
+//! everywhere this tool runs, rather than pulling in an HTTP stack
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Error};
+
+/// The User-Agent we send; crates.io rejects requests without one
+pub const USER_AGENT: &str =
+    "cargo-workspace-version (https://github.com/ava-labs/cargo-workspace-version)";
+
+/// The status and body of a response
+#[derive(Debug)]
+pub struct Response {
+    /// the HTTP status code
+    pub status: u16,
+    /// the body of the response
+    pub body: String,
+}
+
+impl Response {
+    /// Check if the status code is in the 2xx range
+    pub fn ok(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Make an HTTP request
+///
+/// * `method` - GET, POST, and so on
+/// * `url` - where to send it
+/// * `headers` - extra headers, as name/value pairs
+/// * `body` - what to send, if anything
+///
+/// Only failing to talk to the server is an error; any response,
+/// including a 404, is returned for the caller to examine
+///
+/// The URL, headers and body go to curl as a config file on its
+/// stdin, since tokens and webhook URLs on its command line could be
+/// read by anyone on the machine with `ps`
+pub fn request(
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: Option<&str>,
+) -> Result<Response, Error> {
+    let mut config = String::new();
+    let mut option = |name: &str, value: &str| {
+        config.push_str(&format!("{name} = \"{}\"\n", quote(value)));
+    };
+    option("request", method);
+    option("user-agent", USER_AGENT);
+    // print the status code on a line of its own after the body
+    option("write-out", "\n%{http_code}");
+    for (name, value) in headers {
+        option("header", &format!("{name}: {value}"));
+    }
+    // the bodies we send are JSON, so they never start with the @ that
+    // would make curl read a file instead
+    if let Some(body) = body {
+        option("data-binary", body);
+    }
+    option("url", url);
+
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Can't run curl")?;
+    {
+        // dropping stdin at the end of this block lets curl see the end
+        let mut stdin = child.stdin.take().expect("stdin was piped");
+        stdin.write_all(config.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "{method} {} failed: {}",
+            site(url),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    let (body, status) = output.rsplit_once('\n').unwrap_or(("", &output));
+    Ok(Response {
+        status: status.trim().parse().unwrap_or_default(),
+        body: body.to_string(),
+    })
+}
+
+/// Escape a value for a double quoted string in a curl config file
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted
+}
+
+/// The scheme and host of a URL, for errors, since the rest of a
+/// webhook URL is often the secret
+fn site(url: &str) -> &str {
+    let host = url.find("://").map_or(0, |i| i + 3);
+    url[host..].find('/').map_or(url, |end| &url[..host + end])
+}
@@ -0,0 +1,274 @@
+//! Ignore files, so scans of the workspace leave out build output,
+//! vendored sources, and anything else that isn't ours to change
+//!
+//! `.gitignore` files are read as git would, along with
+//! `.git/info/exclude` at the top, and our own [`IGNORE_FILE`] in any
+//! directory, for things git tracks but we should leave alone. The
+//! files are read directly, so this works without git too
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Our own ignore file, in the same format as `.gitignore`
+pub const IGNORE_FILE: &str = ".workspace-version-ignore";
+
+/// The ignore rules that apply in a directory being scanned
+#[derive(Debug, Clone, Default)]
+pub struct Ignore {
+    /// every rule from the directories above, in the order read
+    rules: Vec<Rule>,
+}
+
+/// One line of an ignore file
+#[derive(Debug, Clone)]
+struct Rule {
+    /// the directory the file was in, which the pattern is relative to
+    base: PathBuf,
+    /// the pattern, without any `!`, leading `/`, or trailing `/`
+    pattern: String,
+    /// `!pattern`, which brings back something ignored earlier
+    negated: bool,
+    /// `pattern/`, which only matches directories
+    dir_only: bool,
+    /// whether the pattern is matched against the whole path below
+    /// `base`, instead of only the name, because it has a `/` in it
+    anchored: bool,
+}
+
+impl Ignore {
+    /// The rules for the top of a scan
+    ///
+    /// * `root` - the directory the scan starts at
+    pub fn root(root: &Path) -> Ignore {
+        let mut ignore = Ignore::default();
+        ignore.read(root, &root.join(".git/info/exclude"));
+        ignore.enter(root)
+    }
+
+    /// The rules for a directory below, adding those from its own
+    /// ignore files
+    ///
+    /// * `dir` - the directory about to be scanned
+    pub fn enter(&self, dir: &Path) -> Ignore {
+        let mut ignore = self.clone();
+        ignore.read(dir, &dir.join(".gitignore"));
+        ignore.read(dir, &dir.join(IGNORE_FILE));
+        ignore
+    }
+
+    /// Check if a file or directory should be left out
+    ///
+    /// As in git, the last rule that matches decides
+    ///
+    /// * `path` - the path, as found in the scan
+    /// * `is_dir` - whether it is a directory
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for rule in self.rules.iter().rev() {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>();
+            let text = if rule.anchored {
+                relative.join("/")
+            } else {
+                relative
+                    .last()
+                    .map(|name| name.to_string())
+                    .unwrap_or_default()
+            };
+            if glob_match(&rule.pattern, &text) {
+                return !rule.negated;
+            }
+        }
+        false
+    }
+
+    /// Add the rules in an ignore file, if there is one
+    fn read(&mut self, dir: &Path, file: &Path) {
+        if let Ok(contents) = fs::read_to_string(file) {
+            self.add(dir, &contents);
+        }
+    }
+
+    /// Add the rules in the contents of an ignore file
+    ///
+    /// * `dir` - the directory the file is in
+    /// * `contents` - the lines of the file
+    fn add(&mut self, dir: &Path, contents: &str) {
+        for line in contents.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line.strip_prefix('\\').unwrap_or(line)),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let anchored = line.contains('/');
+            let pattern = line.strip_prefix('/').unwrap_or(line);
+            if pattern.is_empty() {
+                continue;
+            }
+            self.rules.push(Rule {
+                base: dir.to_path_buf(),
+                pattern: pattern.to_string(),
+                negated,
+                dir_only,
+                anchored,
+            });
+        }
+    }
+}
+
+/// Match a path against a glob pattern
+///
+/// `*` matches anything but `/`, `?` one character other than `/`,
+/// `[abc]` and `[a-z]` one of a set (`[!abc]` one not in it), and `**`
+/// any number of whole directories
+///
+/// * `pattern` - the glob pattern
+/// * `text` - the path, with `/` between its parts
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    matches(&pattern, &text)
+}
+
+/// The recursive part of [`glob_match`]
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // no directories, or skip one and try again
+            matches(rest, text)
+                || text
+                    .iter()
+                    .position(|c| *c == '/')
+                    .is_some_and(|slash| matches(pattern, &text[slash + 1..]))
+        }
+        ['*', '*'] => true,
+        ['*', rest @ ..] => {
+            let run = text.iter().take_while(|c| **c != '/').count();
+            (0..=run).any(|skip| matches(rest, &text[skip..]))
+        }
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(c) if *c != '/') && matches(rest, &text[1..])
+        }
+        ['[', rest @ ..] => {
+            let Some((&c, text_rest)) = text.split_first() else {
+                return false;
+            };
+            let (negated, set) = match rest.first() {
+                Some('!' | '^') => (true, &rest[1..]),
+                _ => (false, rest),
+            };
+            // a `]` right at the start is part of the set
+            let Some(end) = set.iter().skip(1).position(|c| *c == ']').map(|i| i + 1) else {
+                // no closing bracket, so it is only a `[`
+                return text.first() == Some(&'[') && matches(rest, &text[1..]);
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < end {
+                if i + 2 < end && set[i + 1] == '-' {
+                    found |= set[i] <= c && c <= set[i + 2];
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && c != '/' && matches(&set[end + 1..], text_rest)
+        }
+        ['\\', escaped, rest @ ..] => text.first() == Some(escaped) && matches(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && matches(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs() {
+        let cases = [
+            ("target", "target", true),
+            ("target", "targets", false),
+            ("*.rs", "main.rs", true),
+            ("*.rs", "src/main.rs", false),
+            ("*", "", true),
+            ("a*c", "abbc", true),
+            ("a*c", "ab/c", false),
+            ("?.toml", "a.toml", true),
+            ("?.toml", "ab.toml", false),
+            ("a?b", "a/b", false),
+            ("[abc].rs", "b.rs", true),
+            ("[abc].rs", "d.rs", false),
+            ("[a-c]x", "cx", true),
+            ("[a-c]x", "dx", false),
+            ("[!a-c]x", "dx", true),
+            ("[^a-c]x", "ax", false),
+            ("[]]", "]", true),
+            ("[]a]", "a", true),
+            ("[!]]", "]", false),
+            ("[!]]", "a", true),
+            ("a[/]b", "a/b", false),
+            ("[ab", "[ab", true),
+            ("[ab", "a", false),
+            ("\\*", "*", true),
+            ("\\*", "a", false),
+            ("**/target", "target", true),
+            ("**/target", "a/b/target", true),
+            ("**/target", "a/targets", false),
+            ("a/**/b", "a/b", true),
+            ("a/**/b", "a/x/y/b", true),
+            ("a/**/b", "ab", false),
+            ("a/**", "a/x/y", true),
+            ("a/**", "a", false),
+            ("crates/*", "crates/foo", true),
+            ("crates/*", "crates/foo/bar", false),
+        ];
+        for (pattern, text, want) in cases {
+            assert_eq!(
+                glob_match(pattern, text),
+                want,
+                "{pattern:?} against {text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let root = Path::new("/ws");
+        let mut ignore = Ignore::default();
+        ignore.add(
+            root,
+            "# build output\ntarget/\n*.bak\n!keep.bak\n/vendor\n\\!odd\n",
+        );
+        let cases = [
+            ("/ws/target", true, true),
+            ("/ws/target", false, false),
+            ("/ws/crates/a/target", true, true),
+            ("/ws/old.bak", false, true),
+            ("/ws/crates/keep.bak", false, false),
+            ("/ws/vendor", true, true),
+            ("/ws/crates/vendor", true, false),
+            ("/ws/!odd", false, true),
+            ("/elsewhere/target", true, false),
+        ];
+        for (path, is_dir, want) in cases {
+            assert_eq!(ignore.is_ignored(Path::new(path), is_dir), want, "{path}");
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! The `init` subcommand: look at the workspace and write a starting
+//! `[workspace.metadata.workspace-version]` table
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Error};
+use toml_edit::{Array, Item, Table, Value};
+
+use crate::{
+    atomic,
+    config::TABLE,
+    encoding,
+    extra::{ExtraFile, Kind},
+    git,
+    ignore::Ignore,
+    version,
+    workspace::Workspace,
+};
+
+/// How deep to look for extra files below the workspace root
+const MAX_DEPTH: usize = 4;
+
+/// Work out a configuration for the workspace and write it to the top
+/// level Cargo.toml
+///
+/// * the tag prefix is the most common prefix of the existing tags
+///   that end in a version
+/// * extra files are those of a kind we handle that already carry the
+///   current version
+/// * members that can't be published are listed to be skipped
+///
+/// * `ws` - the workspace to configure
+/// * `quiet` - don't report what was found
+pub fn run(ws: &mut Workspace, quiet: bool) -> Result<(), Error> {
+    let existing = ws
+        .doc
+        .get("workspace")
+        .and_then(|w| w.get("metadata"))
+        .and_then(|m| m.get(TABLE));
+    if existing.is_some() {
+        bail!(
+            "{} already has a [workspace.metadata.{TABLE}] table; edit it instead",
+            ws.manifest.display()
+        )
+    }
+    let mut table = Table::new();
+    let mut report = vec![];
+
+    if let Some((prefix, count)) = tag_prefix() {
+        table.insert("tag-prefix", Item::Value(prefix.as_str().into()));
+        report.push(format!(
+            "tag-prefix = {prefix:?} (used by {count} existing tag(s))"
+        ));
+    }
+
+    if let Some(current) = ws.current_version() {
+        let mut found = vec![];
+        let mut unnamed = vec![];
+        let root = Path::new(".");
+        let ignore = Ignore::root(root);
+        find_extra_files(root, &ignore, current, 0, &mut found, &mut unnamed);
+        for path in unnamed {
+            report.push(format!(
+                "left out {} (the name isn't valid UTF-8, so it can't go in Cargo.toml)",
+                path.display()
+            ));
+        }
+        found.sort_by(|(_, a), (_, b)| a.cmp(b));
+        for kind in [Kind::Pkgbuild, Kind::Wix, Kind::Maven, Kind::Gradle] {
+            let paths = found
+                .iter()
+                .filter(|(k, _)| *k == kind)
+                .map(|(_, path)| path.as_str())
+                .collect::<Vec<_>>();
+            if paths.is_empty() {
+                continue;
+            }
+            let value: Value = match paths[..] {
+                [path] => path.into(),
+                _ => Value::Array(paths.iter().copied().collect::<Array>()),
+            };
+            report.push(format!(
+                "{} = {} (has version {current})",
+                kind.config_key(),
+                value.to_string().trim()
+            ));
+            table.insert(kind.config_key(), Item::Value(value));
+        }
+    }
+
+    let private = ws
+        .members
+        .iter()
+        .filter(|m| !m.publishable())
+        .filter_map(|m| m.name())
+        .collect::<Vec<_>>();
+    if !private.is_empty() {
+        let value = Value::Array(private.iter().copied().collect::<Array>());
+        report.push(format!(
+            "skip = {} (can't be published)",
+            value.to_string().trim()
+        ));
+        table.insert("skip", Item::Value(value));
+    }
+
+    if table.is_empty() {
+        if !quiet {
+            println!("Found nothing to configure; the defaults will be used");
+        }
+        return Ok(());
+    }
+
+    let Some(workspace) = ws.doc.get_mut("workspace").and_then(Item::as_table_mut) else {
+        bail!("No [workspace] section in top level")
+    };
+    let metadata = workspace.entry("metadata").or_insert_with(|| {
+        let mut metadata = Table::new();
+        metadata.set_implicit(true);
+        Item::Table(metadata)
+    });
+    let Some(metadata) = metadata.as_table_mut() else {
+        bail!("[workspace.metadata] must be a table")
+    };
+    metadata.insert(TABLE, Item::Table(table));
+    atomic::write(
+        &ws.manifest,
+        encoding::like_file(&ws.manifest, ws.doc.to_string()),
+    )?;
+
+    if !quiet {
+        println!(
+            "Wrote [workspace.metadata.{TABLE}] to {}:",
+            ws.manifest.display()
+        );
+        for line in report {
+            println!("  {line}");
+        }
+    }
+    Ok(())
+}
+
+/// The most common prefix of the git tags that end in a version, and
+/// how many tags use it
+fn tag_prefix() -> Option<(String, usize)> {
+    let mut counts = HashMap::<String, usize>::new();
+    for tag in git::tags() {
+        if let Some((prefix, _)) = version::split_tag(&tag) {
+            *counts.entry(prefix.to_string()).or_default() += 1;
+        }
+    }
+    // ties go to the shorter prefix, so the result doesn't depend on
+    // the order of the map
+    counts
+        .into_iter()
+        .max_by(|(a, x), (b, y)| x.cmp(y).then(b.len().cmp(&a.len())).then(b.cmp(a)))
+}
+
+/// Look for files we know how to update that hold the current version
+///
+/// A file counts if we can find a version in it and that version is
+/// already the current one, so unrelated files with the same name are
+/// left out
+///
+/// * `dir` - the directory to look in
+/// * `ignore` - the ignore rules for `dir`; anything they match is left
+///   out
+/// * `current` - the current version of the workspace
+/// * `depth` - how far below the workspace root `dir` is
+/// * `found` - where to add each file, by kind, with its path
+/// * `unnamed` - where to add anything left out because its name isn't
+///   valid UTF-8, and so can't be written in the configuration
+fn find_extra_files(
+    dir: &Path,
+    ignore: &Ignore,
+    current: &str,
+    depth: usize,
+    found: &mut Vec<(Kind, String)>,
+    unnamed: &mut Vec<PathBuf>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if ignore.is_ignored(&path, path.is_dir()) {
+            continue;
+        }
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            // hidden ones would have been passed over anyway
+            if !name.to_string_lossy().starts_with('.') {
+                unnamed.push(path.strip_prefix(".").unwrap_or(&path).to_path_buf());
+            }
+            continue;
+        };
+        if path.is_dir() {
+            // build output and hidden directories are never worth a look
+            if depth < MAX_DEPTH && !name.starts_with('.') && name != "target" {
+                let ignore = ignore.enter(&path);
+                find_extra_files(&path, &ignore, current, depth + 1, found, unnamed);
+            }
+            continue;
+        }
+        let kind = match name {
+            "PKGBUILD" => Kind::Pkgbuild,
+            "wix.toml" => Kind::Wix,
+            _ if name.ends_with(".wxs") => Kind::Wix,
+            "pom.xml" => Kind::Maven,
+            "gradle.properties" => Kind::Gradle,
+            _ => continue,
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let relative = path.strip_prefix(".").unwrap_or(&path);
+        let file = ExtraFile {
+            kind,
+            path: relative.to_path_buf(),
+        };
+        // a different version has to produce an edit, or we didn't
+        // find a version in it at all
+        let has_version = file
+            .rewrite(&contents, "0.0.0")
+            .is_ok_and(|(_, edits)| !edits.is_empty());
+        let is_current = file
+            .rewrite(&contents, current)
+            .is_ok_and(|(_, edits)| edits.is_empty());
+        if has_version && is_current {
+            // every part was checked on the way down, so nothing is lost
+            let parts = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>();
+            found.push((kind, parts.join("/")));
+        }
+    }
+}
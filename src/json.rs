@@ -0,0 +1,476 @@
+//! Just enough JSON to produce machine readable output and to read
+//! the responses of the web APIs we talk to
+use std::fmt::{self, Display, Formatter, Write};
+
+use anyhow::{anyhow, bail, Error};
+
+/// A JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// any number
+    Number(f64),
+    /// a string, escaped when printed
+    String(String),
+    /// an array of values
+    Array(Vec<Json>),
+    /// an object, with keys kept in the order they were added
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Start building an empty object
+    pub fn object() -> Json {
+        Json::Object(vec![])
+    }
+
+    /// Add a key to an object, for chaining
+    ///
+    /// Does nothing if this isn't an object
+    pub fn with<K: Into<String>, V: Into<Json>>(mut self, key: K, value: V) -> Json {
+        if let Json::Object(entries) = &mut self {
+            entries.push((key.into(), value.into()));
+        }
+        self
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(v: Option<T>) -> Json {
+        v.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(v: Vec<T>) -> Json {
+        Json::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl Display for Json {
+    /// Values are printed on one line; use `{:#}` to indent them
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_value(self, f, if f.alternate() { Some(0) } else { None })
+    }
+}
+
+/// Write a value, indenting by `indent` levels if requested
+fn write_value(value: &Json, f: &mut Formatter<'_>, indent: Option<usize>) -> fmt::Result {
+    let newline = |f: &mut Formatter<'_>, level: usize| -> fmt::Result {
+        if indent.is_some() {
+            write!(f, "\n{:width$}", "", width = level * 2)?;
+        }
+        Ok(())
+    };
+    let level = indent.unwrap_or_default();
+    let inner = indent.map(|i| i + 1);
+    match value {
+        Json::Null => f.write_str("null"),
+        Json::Bool(b) => write!(f, "{b}"),
+        Json::Number(n) if n.is_finite() => write!(f, "{n}"),
+        Json::Number(_) => f.write_str("null"),
+        Json::String(s) => write_string(s, f),
+        Json::Array(values) if values.is_empty() => f.write_str("[]"),
+        Json::Array(values) => {
+            f.write_char('[')?;
+            for (i, v) in values.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                newline(f, level + 1)?;
+                write_value(v, f, inner)?;
+            }
+            newline(f, level)?;
+            f.write_char(']')
+        }
+        Json::Object(entries) if entries.is_empty() => f.write_str("{}"),
+        Json::Object(entries) => {
+            f.write_char('{')?;
+            for (i, (k, v)) in entries.iter().enumerate() {
+                if i > 0 {
+                    f.write_char(',')?;
+                }
+                newline(f, level + 1)?;
+                write_string(k, f)?;
+                f.write_str(if indent.is_some() { ": " } else { ":" })?;
+                write_value(v, f, inner)?;
+            }
+            newline(f, level)?;
+            f.write_char('}')
+        }
+    }
+}
+
+/// Write a quoted and escaped string
+fn write_string(s: &str, f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Json {
+    /// Parse a JSON document
+    pub fn parse(text: &str) -> Result<Json, Error> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos != parser.text.len() {
+            bail!("trailing characters in JSON at offset {}", parser.pos)
+        }
+        Ok(value)
+    }
+
+    /// Look up a key, if this is an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The values, if this is an array
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// The number, if this is a number
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The string, if this is a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// How deeply arrays and objects may be nested, so a hostile response
+/// can't overflow the stack
+const MAX_DEPTH: usize = 128;
+
+/// A recursive descent parser over the bytes of a document
+struct Parser<'a> {
+    /// the whole document
+    text: &'a [u8],
+    /// where we are in the document
+    pos: usize,
+    /// how many arrays and objects we are inside of
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Skip whitespace and return the next byte without consuming it
+    fn peek(&mut self) -> Option<u8> {
+        self.whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        if self.peek() != Some(byte) {
+            bail!("expected '{}' in JSON at offset {}", byte as char, self.pos)
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, Error> {
+        if !self.text[self.pos..].starts_with(word.as_bytes()) {
+            bail!("invalid JSON at offset {}", self.pos)
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        if matches!(self.peek(), Some(b'{' | b'[')) {
+            if self.depth == MAX_DEPTH {
+                bail!("JSON nested too deeply at offset {}", self.pos)
+            }
+            self.depth += 1;
+            let value = self.nested();
+            self.depth -= 1;
+            return value;
+        }
+        self.scalar()
+    }
+
+    /// An object or an array
+    fn nested(&mut self) -> Result<Json, Error> {
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut entries = vec![];
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(entries));
+                }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    entries.push((key, self.value()?));
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b'}')?;
+                Ok(Json::Object(entries))
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = vec![];
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b']')?;
+                Ok(Json::Array(values))
+            }
+            _ => self.scalar(),
+        }
+    }
+
+    /// A string, number, boolean or null
+    fn scalar(&mut self) -> Result<Json, Error> {
+        match self.peek() {
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(_) => {
+                let start = self.pos;
+                while self
+                    .text
+                    .get(self.pos)
+                    .is_some_and(|b| matches!(b, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+                {
+                    self.pos += 1;
+                }
+                std::str::from_utf8(&self.text[start..self.pos])?
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| anyhow!("invalid JSON at offset {start}"))
+            }
+            None => bail!("unexpected end of JSON"),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut out = vec![];
+        loop {
+            let Some(&byte) = self.text.get(self.pos) else {
+                bail!("unterminated string in JSON")
+            };
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.text.get(self.pos) else {
+                        bail!("unterminated string in JSON")
+                    };
+                    self.pos += 1;
+                    match escape {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'u' => {
+                            let c = self.unicode_escape()?;
+                            out.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => out.push(other),
+                    }
+                }
+                other => out.push(other),
+            }
+        }
+        Ok(String::from_utf8(out)?)
+    }
+
+    /// Decode the hex digits after `\u`, including surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let hex = |p: &mut Self| -> Result<u32, Error> {
+            let digits = p
+                .text
+                .get(p.pos..p.pos + 4)
+                .ok_or(anyhow!("truncated \\u escape in JSON"))?;
+            p.pos += 4;
+            Ok(u32::from_str_radix(std::str::from_utf8(digits)?, 16)?)
+        };
+        let first = hex(self)?;
+        let code = if (0xd800..0xdc00).contains(&first) && self.text[self.pos..].starts_with(b"\\u")
+        {
+            self.pos += 2;
+            let second = hex(self)?;
+            0x10000 + ((first - 0xd800) << 10) + (second.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            first
+        };
+        Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes() {
+        let cases = [
+            (r#""plain""#, "plain"),
+            (r#""a\"b\\c\/d""#, "a\"b\\c/d"),
+            (r#""\n\r\t\b\f""#, "\n\r\t\u{8}\u{c}"),
+            (r#""\u00e9\u00E9""#, "éé"),
+            (r#""\ud83d\ude00""#, "😀"),
+            (r#""\ud83d""#, "\u{fffd}"),
+            (r#""\ude00""#, "\u{fffd}"),
+            ("\"日本\"", "日本"),
+        ];
+        for (text, want) in cases {
+            assert_eq!(Json::parse(text).unwrap(), Json::from(want), "{text}");
+        }
+    }
+
+    #[test]
+    fn numbers() {
+        let cases = [
+            ("0", 0.0),
+            ("-1.5", -1.5),
+            ("1e3", 1000.0),
+            ("2.5E-2", 0.025),
+            ("12345678", 12345678.0),
+        ];
+        for (text, want) in cases {
+            assert_eq!(Json::parse(text).unwrap().as_number(), Some(want), "{text}");
+        }
+    }
+
+    #[test]
+    fn nesting() {
+        let json = Json::parse(r#" { "a" : [1, {"b": null}, []], "c": true, "d": {} } "#).unwrap();
+        let a = json.get("a").and_then(Json::as_array).unwrap();
+        assert_eq!(a[0], Json::Number(1.0));
+        assert_eq!(a[1].get("b"), Some(&Json::Null));
+        assert_eq!(a[2], Json::Array(vec![]));
+        assert_eq!(json.get("c"), Some(&Json::Bool(true)));
+        assert_eq!(json.get("d"), Some(&Json::object()));
+        assert_eq!(json.get("e"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = Json::object()
+            .with("text", "quote \" slash \\ newline \n control \u{1} é")
+            .with(
+                "list",
+                vec![Json::Null, Json::from(2usize), Json::from(false)],
+            )
+            .with("empty", Json::object());
+        for printed in [json.to_string(), format!("{json:#}")] {
+            assert_eq!(Json::parse(&printed).unwrap(), json, "{printed}");
+        }
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+    }
+
+    #[test]
+    fn malformed() {
+        let deep = "[".repeat(100_000);
+        let cases = [
+            "",
+            "   ",
+            "{",
+            "[1,",
+            "[1,]",
+            "{,}",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            r#"{1: 2}"#,
+            "tru",
+            "nul",
+            "\"abc",
+            "\"\\",
+            r#""\u12""#,
+            r#""\uZZZZ""#,
+            r#""\ud83d\u""#,
+            "-",
+            "1e",
+            "1.2.3",
+            "1 2",
+            "[] x",
+            &deep,
+        ];
+        for text in cases {
+            assert!(Json::parse(text).is_err(), "{text:.20}");
+        }
+    }
+}
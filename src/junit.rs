@@ -0,0 +1,128 @@
+//! The results of `check` or `update` as JUnit XML, which Jenkins,
+//! GitLab and most other CI systems show as test results
+use std::fmt::Write;
+
+use anyhow::Error;
+
+use crate::{
+    report::{self, Difference},
+    workspace::Workspace,
+};
+
+/// The name of the test suite, and the class of every test case
+const SUITE: &str = "workspace-version";
+
+/// Render the results as a JUnit XML test suite
+///
+/// Each member is a test case, which fails when a check finds any of
+/// its versions wrong. Other files with differences, like the top
+/// level manifest or an extra file, get a test case of their own, and
+/// an error that stopped the run is one more
+///
+/// * `ws` - the workspace, as it was left by the update
+/// * `newver` - the version that was checked for or applied
+/// * `writing` - whether the values were changed, or only checked
+/// * `differences` - everything that didn't have the version
+/// * `error` - why the run failed, if it did for some other reason
+pub fn render(
+    ws: &Workspace,
+    newver: &str,
+    writing: bool,
+    differences: &[Difference],
+    error: Option<&Error>,
+) -> String {
+    // each case is a name and the differences found for it
+    let mut cases = ws
+        .members
+        .iter()
+        .map(|m| {
+            let name = m
+                .name()
+                .map_or(m.manifest.display().to_string(), String::from);
+            let found = differences
+                .iter()
+                .filter(|d| d.change.path == m.manifest)
+                .collect::<Vec<_>>();
+            (name, found)
+        })
+        .collect::<Vec<_>>();
+    for path in report::files(differences) {
+        if !ws.members.iter().any(|m| m.manifest == path) {
+            let found = differences
+                .iter()
+                .filter(|d| d.change.path == path)
+                .collect();
+            cases.push((report::display_path(path), found));
+        }
+    }
+    let error = error.filter(|e| !e.is::<report::DifferencesFound>());
+    let failures = if writing {
+        0
+    } else {
+        cases.iter().filter(|(_, found)| !found.is_empty()).count()
+    };
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuite name=\"{SUITE}\" tests=\"{}\" failures=\"{failures}\" errors=\"{}\">",
+        cases.len() + usize::from(error.is_some()),
+        usize::from(error.is_some())
+    );
+    let _ = writeln!(
+        out,
+        "  <properties>\n    <property name=\"version\" value=\"{}\"/>\n  </properties>",
+        escape(newver)
+    );
+    for (name, found) in &cases {
+        let _ = write!(
+            out,
+            "  <testcase name=\"{}\" classname=\"{SUITE}\"",
+            escape(name)
+        );
+        let text = found
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if found.is_empty() {
+            out.push_str("/>\n");
+        } else if writing {
+            // fixed by the update, so there is nothing to fail
+            let _ = writeln!(
+                out,
+                ">\n    <system-out>{}</system-out>\n  </testcase>",
+                escape(&text)
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                ">\n    <failure message=\"{}\" type=\"version\">{}</failure>\n  </testcase>",
+                escape(&format!(
+                    "{} value(s) don't have version {newver}",
+                    found.len()
+                )),
+                escape(&text)
+            );
+        }
+    }
+    if let Some(e) = error {
+        let _ = writeln!(
+            out,
+            "  <testcase name=\"workspace\" classname=\"{SUITE}\">\n    \
+             <error message=\"{}\"/>\n  </testcase>",
+            escape(&format!("{e:#}"))
+        );
+    }
+    out.push_str("</testsuite>\n");
+    out
+}
+
+/// Escape text for XML, in content or an attribute
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
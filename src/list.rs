@@ -0,0 +1,172 @@
+//! The `list` subcommand: a table of every member and its version
+use std::collections::HashMap;
+
+use anyhow::Error;
+use toml_edit::Item;
+
+use crate::{
+    json::Json,
+    workspace::{dependency_package, DeclaredVersion, Member, Workspace},
+    Format,
+};
+
+/// A dependency on another member whose requirement doesn't match
+/// the version of that member
+#[derive(Debug)]
+struct Mismatch {
+    /// the name of the dependency
+    dependency: String,
+    /// the version requirement written in the manifest
+    requirement: String,
+    /// the version the member actually has
+    version: String,
+    /// the requirement that should be written, in the configured style
+    expected: String,
+}
+
+/// Print every member with its declared version and whether its
+/// dependencies on other members are consistent
+///
+/// * `ws` - the workspace to list
+/// * `format` - text for a table, or JSON for tooling
+pub fn print(ws: &Workspace, format: Format) -> Result<(), Error> {
+    // the version each member really has, after inheritance, so we
+    // can compare dependency requirements against it
+    let versions = ws
+        .members
+        .iter()
+        .filter_map(|m| Some((m.name()?, ws.member_version(m)?)))
+        .collect::<HashMap<_, _>>();
+
+    let rows = ws
+        .members
+        .iter()
+        .map(|m| (m, mismatches(ws, m, &versions)))
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Text => print_table(&rows),
+        Format::Json => {
+            let members = rows
+                .iter()
+                .map(|(m, mismatches)| {
+                    let (version, inherited) = match m.declared_version() {
+                        DeclaredVersion::Version(v) => (Some(v), false),
+                        DeclaredVersion::Inherited => (ws.package_version(), true),
+                        DeclaredVersion::Missing => (None, false),
+                    };
+                    let mismatches = mismatches
+                        .iter()
+                        .map(|mm| {
+                            Json::object()
+                                .with("dependency", mm.dependency.as_str())
+                                .with("requirement", mm.requirement.as_str())
+                                .with("version", mm.version.as_str())
+                                .with("expected", mm.expected.as_str())
+                        })
+                        .collect::<Vec<_>>();
+                    Json::object()
+                        .with("name", m.name())
+                        .with("path", m.dir.as_str())
+                        .with("version", version)
+                        .with("inherited", inherited)
+                        .with("consistent", mismatches.is_empty())
+                        .with("mismatches", mismatches)
+                })
+                .collect::<Vec<_>>();
+            println!("{:#}", Json::from(members));
+        }
+    }
+    Ok(())
+}
+
+/// Print the human readable form, with aligned columns
+fn print_table(rows: &[(&Member, Vec<Mismatch>)]) {
+    let lines = rows
+        .iter()
+        .map(|(m, mismatches)| {
+            let version = match m.declared_version() {
+                DeclaredVersion::Version(v) => v.to_string(),
+                DeclaredVersion::Inherited => "inherited".to_string(),
+                DeclaredVersion::Missing => "-".to_string(),
+            };
+            let deps = if mismatches.is_empty() {
+                "ok".to_string()
+            } else {
+                mismatches
+                    .iter()
+                    .map(|mm| {
+                        if mm.requirement.trim_start_matches(['=', '~', '^']) == mm.version {
+                            format!(
+                                "{} is written {} instead of {}",
+                                mm.dependency, mm.requirement, mm.expected
+                            )
+                        } else {
+                            format!(
+                                "{} wants {} but is {}",
+                                mm.dependency, mm.requirement, mm.version
+                            )
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            [
+                m.name().unwrap_or("-").to_string(),
+                m.dir.clone(),
+                version,
+                deps,
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let header = ["NAME", "PATH", "VERSION", "DEPS"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for line in &lines {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for line in std::iter::once(&header).chain(&lines) {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            line[0],
+            line[1],
+            line[2],
+            line[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+    }
+}
+
+/// Find the dependencies on other members whose version requirement
+/// doesn't match the version of that member
+///
+/// A requirement only matches when it is written in the configured
+/// style, if there is one, so `=1.2.3` doesn't match 1.2.3 when the
+/// style is caret
+///
+/// * `ws` - the workspace, for the requirement style
+/// * `member` - the member whose dependencies we check
+/// * `versions` - the version of each member, by package name
+fn mismatches(ws: &Workspace, member: &Member, versions: &HashMap<&str, &str>) -> Vec<Mismatch> {
+    let Some(deps) = member.doc.get("dependencies").and_then(Item::as_table) else {
+        return vec![];
+    };
+    deps.iter()
+        .filter_map(|(name, dep)| {
+            let package = dependency_package(name, dep);
+            let version = versions.get(package)?;
+            let requirement = dep.get("version")?.as_str()?;
+            let expected = ws.config.requirement(package, version, Some(requirement));
+            (requirement != expected).then(|| Mismatch {
+                dependency: name.to_string(),
+                requirement: requirement.to_string(),
+                version: version.to_string(),
+                expected,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,58 @@
+//! An advisory lock so that concurrent runs (such as parallel CI jobs)
+//! can't interleave their writes to the manifests
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Error};
+
+/// The name of the lock file inside the target directory
+const LOCK_FILE: &str = "workspace-version.lock";
+
+/// Holds the lock until it is dropped
+///
+/// The operating system releases the lock when the file is closed, so
+/// a run that is killed can't leave a stale lock behind
+#[derive(Debug)]
+pub struct Lock {
+    _file: File,
+}
+
+/// Take the lock, waiting for any other run to finish first
+///
+/// The lock file lives in the cargo target directory (`CARGO_TARGET_DIR`
+/// or `target/`), which is created if needed, so it never shows up as
+/// a change in the repository
+///
+/// * `quiet` - don't say anything if we have to wait
+pub fn acquire(quiet: bool) -> Result<Lock, Error> {
+    let dir = std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("target"));
+    std::fs::create_dir_all(&dir).context(format!("Can't create {}", dir.display()))?;
+    let path = dir.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .context(format!("Can't open {}", path.display()))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            if !quiet {
+                println!(
+                    "Waiting for another run to finish (lock held on {})",
+                    path.display()
+                );
+            }
+            file.lock()
+                .context(format!("Can't lock {}", path.display()))?;
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(e).context(format!("Can't lock {}", path.display()))
+        }
+    }
+    Ok(Lock { _file: file })
+}
@@ -2,26 +2,357 @@
 #![warn(missing_docs, missing_debug_implementations, rust_2018_idioms)]
 //! Release tool to update all versions of everything
 //! inside the crate at the same time to the same version
-use std::{collections::HashSet, fs, path::PathBuf};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
 use anyhow::{anyhow, bail, Context, Error};
-use clap::{Parser, Subcommand};
-use toml_edit::{Document, Formatted, InlineTable, Item, KeyMut, Value};
+use clap::{Parser, Subcommand, ValueEnum};
+use toml_edit::{Document, InlineTable, Item, KeyMut, TableLike, Value};
+
+mod atomic;
+mod audit;
+mod calver;
+mod cargo_release;
+mod color;
+mod commits;
+mod compare;
+mod config;
+mod dep_update;
+mod dev;
+mod diagnostics;
+mod diff;
+mod diff_tool;
+mod doctor;
+mod encoding;
+mod extra;
+mod get;
+mod git;
+mod github;
+mod gitlab;
+mod hooks;
+mod html;
+mod http;
+mod ignore;
+mod init;
+mod json;
+mod junit;
+mod list;
+mod lock;
+mod markdown;
+mod metadata;
+mod notify;
+mod policy;
+mod progress;
+mod registry;
+mod report;
+mod sarif;
+mod status;
+mod target;
+mod trace;
+mod verify_tags;
+mod version;
+mod workspace;
+
+use color::Paint;
+use config::Config;
+use extra::ExtraFile;
+use workspace::Workspace;
 
 #[derive(Debug, Subcommand)]
 enum SubCommand {
-    Update { newver: String },
-    Check { newver: String },
+    Update {
+        #[command(flatten)]
+        newver: VersionSource,
+        /// How to report what was done
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+        /// Show the diff of every file that would change, without
+        /// writing anything; hooks are told with WSV_DRY_RUN=1, --verify
+        /// commands aren't run, and unlike check, this doesn't fail when
+        /// something would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    Check {
+        #[command(flatten)]
+        newver: VersionSource,
+        /// How to report what was found
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
+        /// Also post the result as a GitHub check run, with an annotation
+        /// on each file that has the wrong version
+        #[arg(long)]
+        github_check: bool,
+        /// Also post the result as a comment on the pull request (or
+        /// GitLab merge request), updating it on later runs
+        #[arg(long)]
+        pr_comment: bool,
+        /// Fail if any version is still the development placeholder that
+        /// `dev` sets; no version has to be given with this
+        #[arg(long)]
+        not_placeholder: bool,
+    },
+    /// Print the current workspace version
+    Get {
+        /// How to print the version
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Print a table of every member, its version, and whether its
+    /// dependencies on other members are consistent
+    List {
+        /// How to print the table
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Show the workspace version, the latest git tag, the latest
+    /// published version of each crate, and the state of the changelog
+    Status {
+        /// Don't ask crates.io for published versions
+        #[arg(long)]
+        offline: bool,
+        /// How to print the status
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Look for problems with the workspace and suggest how to fix them
+    Doctor,
+    /// Compare the versions of each member published to crates.io with
+    /// the release tags in the repository
+    Audit {
+        /// How to print the results
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Check that each past release tag points at a commit whose
+    /// manifest has the version the tag is named for
+    VerifyTags {
+        /// How to print the results
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Show how the version of each crate changed between two git refs
+    /// or two directories
+    Compare {
+        /// The earlier workspace, as a git ref or a directory
+        old: String,
+        /// The later workspace, as a git ref or a directory
+        #[arg(default_value = ".")]
+        new: String,
+        /// Only list crates whose version changed
+        #[arg(long)]
+        changed: bool,
+        /// How to print the results
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Write a starting [workspace.metadata.workspace-version] table,
+    /// based on what is found in the workspace
+    Init,
+    /// Work with the [workspace.metadata.workspace-version] settings
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Compute the next version from the current one and update
+    /// everything to it
+    Bump {
+        /// Which part of the version to increment
+        #[arg(value_enum)]
+        level: BumpLevel,
+        /// The pre-release identifier to use, like rc or beta; defaults
+        /// to the one the current version has
+        #[arg(long, visible_alias = "tag")]
+        id: Option<String>,
+    },
+    /// Print the version bump would apply, without changing anything
+    Next {
+        /// Which part of the version to increment; with --scheme calver
+        /// it can be left out for the next calendar version
+        #[arg(value_enum)]
+        level: Option<BumpLevel>,
+        /// The pre-release identifier to use, like rc or beta; defaults
+        /// to the one the current version has
+        #[arg(long, visible_alias = "tag")]
+        id: Option<String>,
+    },
+    /// Turn the current pre-release into the release it previews, like
+    /// 1.4.0-rc.3 into 1.4.0, and update everything to it
+    Promote,
+    /// Set every version to the development placeholder, and remove the
+    /// version from path requirements on other members
+    Dev,
+    /// Point the rev of a git dependency at the latest commit of its
+    /// repository, everywhere in the workspace it is used
+    DepUpdate {
+        /// The package name of the git dependency
+        #[arg(long)]
+        git: String,
+        /// The branch or tag to resolve; defaults to the default branch
+        #[arg(long = "ref")]
+        reference: Option<String>,
+    },
+}
+
+/// Where the version to update to (or check for) comes from
+#[derive(Debug, clap::Args)]
+#[group(multiple = false)]
+struct VersionSource {
+    /// The version, or an alias like next-minor (or next, for calendar
+    /// versions), or an expression like {major}.{minor+1}.0; check
+    /// without one checks everything has the workspace version
+    newver: Option<String>,
+    /// Take the version from the tag a CI pipeline was started for,
+    /// like GITHUB_REF_NAME or CI_COMMIT_TAG
+    #[arg(long)]
+    from_ci: bool,
+    /// Take the version from an environment variable, like
+    /// RELEASE_VERSION
+    #[arg(long, value_name = "NAME")]
+    from_env: Option<String>,
+    /// Take the version from a file that has only the version in it,
+    /// like VERSION
+    #[arg(long, value_name = "FILE")]
+    from_file: Option<PathBuf>,
+    /// Take the version from the newest release tag reachable from
+    /// HEAD, or the release after it at the given level
+    #[arg(long, value_enum, value_name = "LEVEL", num_args = 0..=1)]
+    from_git: Option<Option<version::Level>>,
+    /// Take the version from the newest release in CHANGELOG.md
+    #[arg(long)]
+    from_changelog: bool,
+}
+
+impl VersionSource {
+    /// Work out the version, without any leading 'v'
+    ///
+    /// * `ws` - the workspace, for the current version and tag prefix
+    /// * `scheme` - how versions are numbered
+    fn resolve(&self, ws: &Workspace, scheme: version::Scheme) -> Result<String, Error> {
+        if let Some(newver) = &self.newver {
+            target::resolve(newver, ws, scheme)
+        } else if self.from_ci {
+            target::from_ci(ws)
+        } else if let Some(name) = &self.from_env {
+            target::from_env(name, ws, scheme)
+        } else if let Some(path) = &self.from_file {
+            target::from_file(path, ws, scheme)
+        } else if let Some(level) = self.from_git {
+            target::from_git(ws, level)
+        } else if self.from_changelog {
+            target::from_changelog()
+        } else {
+            bail!("No version was given; pass one, or one of the --from options")
+        }
+    }
+
+    /// Check if a version was asked for at all
+    fn given(&self) -> bool {
+        self.newver.is_some()
+            || self.from_ci
+            || self.from_env.is_some()
+            || self.from_file.is_some()
+            || self.from_git.is_some()
+            || self.from_changelog
+    }
 }
 
-impl SubCommand {
-    fn version(&self) -> &String {
+/// What to do with the settings
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Print the JSON Schema of the settings, for editor integration
+    Schema,
+}
+
+/// What `bump` increments
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BumpLevel {
+    /// The major version, so 1.2.3 becomes 2.0.0
+    Major,
+    /// The minor version, so 1.2.3 becomes 1.3.0
+    Minor,
+    /// The patch version, so 1.2.3 becomes 1.2.4
+    Patch,
+    /// The pre-release counter, so rc.1 becomes rc.2
+    Pre,
+    /// Whichever of major, minor or patch the Conventional Commits
+    /// since the last release tag call for
+    Auto,
+}
+
+impl BumpLevel {
+    /// The version after the current one at this level
+    ///
+    /// * `ws` - the workspace, for the current version
+    /// * `id` - the pre-release identifier, for `pre`
+    fn next(self, ws: &Workspace, id: Option<&str>) -> Result<String, Error> {
+        if id.is_some() && !matches!(self, BumpLevel::Pre) {
+            bail!("--id (or --tag) only goes with pre")
+        }
         match self {
-            SubCommand::Update { newver } | SubCommand::Check { newver } => newver,
+            BumpLevel::Major => target::next(ws, version::Level::Major),
+            BumpLevel::Minor => target::next(ws, version::Level::Minor),
+            BumpLevel::Patch => target::next(ws, version::Level::Patch),
+            BumpLevel::Pre => target::prerelease(ws, id),
+            BumpLevel::Auto => target::next(ws, commits::level(&ws.config)?),
         }
     }
 }
 
+/// How a run went, when it didn't fail
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    /// there was nothing to report
+    Clean,
+    /// nothing is wrong, but there were warnings
+    Warnings,
+}
+
+/// Output formats for commands that report information
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum Format {
+    /// Human readable text
+    #[default]
+    Text,
+    /// JSON, for tooling
+    Json,
+}
+
+/// What the workspaces found by --recursive are given
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Recursion {
+    /// Each works out its own version, from its own current one
+    Independent,
+    /// Each gets the version worked out for the top workspace
+    Same,
+}
+
+/// Output formats for the results of `check` and `update`
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+enum ReportFormat {
+    /// Each difference as it is found
+    #[default]
+    Text,
+    /// A standalone HTML page, printed at the end
+    Html,
+    /// A Markdown table for a pull request, printed at the end
+    Markdown,
+    /// cargo's JSON messages, as with --message-format json, for editors
+    CargoJson,
+    /// One JSON document with every file looked at and each version in
+    /// it, printed at the end
+    Json,
+    /// SARIF, for GitHub code scanning, printed at the end
+    Sarif,
+    /// JUnit XML with a test case for each crate, printed at the end
+    Junit,
+}
+
 #[derive(Debug, Parser)]
 struct Args {
     /// how cargo invoked this; cargo chews up the first argument
@@ -35,142 +366,1361 @@ struct Args {
     /// Don't print anything
     #[arg(short, long)]
     quiet: bool,
+
+    /// Say more: -v also lists the files that were already right, and
+    /// -vv every dependency looked at, with why any were skipped
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Remove any +build metadata, from the requested version and when
+    /// comparing against the versions in the manifests
+    #[arg(long, global = true, conflicts_with = "metadata")]
+    strip_metadata: bool,
+
+    /// Add build metadata to the version: git-sha for the commit,
+    /// env:NAME for an environment variable, or the text itself
+    #[arg(long, global = true, value_name = "SOURCE")]
+    metadata: Option<String>,
+
+    /// The top level Cargo.toml of the workspace, if it isn't the one
+    /// in the current directory
+    #[arg(long, global = true, value_name = "PATH")]
+    manifest_path: Option<PathBuf>,
+
+    /// Use these members instead of the ones listed in the top level
+    /// Cargo.toml, for this run only
+    #[arg(long, global = true, value_delimiter = ',', value_name = "DIRS")]
+    members: Vec<String>,
+
+    /// Ask cargo metadata for the members, so they are exactly the ones
+    /// cargo sees, instead of reading workspace.members ourselves
+    #[arg(long, global = true, conflicts_with = "members")]
+    cargo_metadata: bool,
+
+    /// Only work on the members in workspace.default-members, so the
+    /// others keep their own versions
+    #[arg(long, global = true, conflicts_with = "members")]
+    default_members_only: bool,
+
+    /// Skip the top level Cargo.toml, and only work on the members
+    #[arg(long, global = true, conflicts_with = "root_only")]
+    no_root: bool,
+
+    /// Only work on the top level Cargo.toml, skipping the members and
+    /// any extra files
+    #[arg(long, global = true)]
+    root_only: bool,
+
+    /// Only work on the versions of packages, leaving requirements on
+    /// other members alone
+    #[arg(long, global = true, conflicts_with = "deps_only")]
+    no_deps: bool,
+
+    /// Only work on requirements on other members, leaving the versions
+    /// of packages and any extra files alone
+    #[arg(long, global = true)]
+    deps_only: bool,
+
+    /// Also rewrite the tag of git dependencies on members, like
+    /// { git = "...", tag = "v1.2.3" }, to the tag for the new version
+    #[arg(long, global = true)]
+    git_tags: bool,
+
+    /// Write the new version into every requirement on members, even
+    /// those that already allow it, like 1.2 does 1.2.5, which are
+    /// otherwise left alone
+    #[arg(long, global = true)]
+    exact_requirements: bool,
+
+    /// Add the version to dependencies on members that only have a
+    /// path, which cargo needs to publish them
+    #[arg(long, global = true)]
+    add_missing_dep_version: bool,
+
+    /// Add a version to members that have none, inheriting the one in
+    /// [workspace.package] if there is one, instead of stopping
+    #[arg(long, global = true)]
+    set_missing: bool,
+
+    /// Remove the version from dependencies on members that have a path,
+    /// for workspaces that are never published; check reports any that
+    /// still have one
+    #[arg(long, global = true, conflicts_with = "add_missing_dep_version")]
+    strip_dep_versions: bool,
+
+    /// Also write a report to a file, like junit=report.xml, in any of
+    /// the --format formats; can be given more than once
+    #[arg(
+        long = "report",
+        global = true,
+        value_name = "FORMAT=PATH",
+        value_parser = parse_report
+    )]
+    reports: Vec<(ReportFormat, PathBuf)>,
+
+    /// Print only one line per change, for scripts: the file, where the
+    /// value is, and its old and new value, separated by tabs
+    #[arg(long, global = true)]
+    porcelain: bool,
+
+    /// A command to run after an update to check the result, like
+    /// "cargo check", split on spaces; it gets the same environment as
+    /// the hooks, can be given more than once, fails the run if it
+    /// fails, and isn't run by update --dry-run
+    #[arg(long, global = true, value_name = "CMD")]
+    verify: Vec<String>,
+
+    /// Show a progress bar while working on the members, when printing
+    /// to a terminal
+    #[arg(long, global = true)]
+    progress: bool,
+
+    /// When to color the output: mismatches in red, fixes in green, and
+    /// explanations dimmed
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t)]
+    color: color::When,
+
+    /// Explain why each difference was reported and why entries were skipped
+    #[arg(long, global = true)]
+    explain: bool,
+
+    /// Leave out pre-release versions when comparing against git tags
+    /// or versions published to crates.io
+    #[arg(long, global = true)]
+    ignore_prerelease: bool,
+
+    /// Use the version even if it isn't a semantic version
+    #[arg(long, global = true)]
+    allow_non_semver: bool,
+
+    /// Let update and bump apply a version lower than the current one
+    #[arg(long, global = true)]
+    allow_downgrade: bool,
+
+    /// How versions are numbered; calver checks for versions like
+    /// 2024.6.1, and lets `update next` and `next` without a level
+    /// compute the one for the current month
+    #[arg(long, global = true, value_enum, default_value_t)]
+    scheme: version::Scheme,
+
+    /// Fail unless the new version is at least this much more than the
+    /// baseline version
+    #[arg(long, global = true, value_enum, value_name = "LEVEL")]
+    require_semver_increase: Option<version::Level>,
+
+    /// What --require-semver-increase compares with: a git ref or tag,
+    /// or crates.io for the newest published version; defaults to the
+    /// latest release tag
+    #[arg(long, global = true, value_name = "BASELINE")]
+    baseline: Option<String>,
+
+    /// Write every change made (or that would be made) to this file as
+    /// a JSON list of {file, toml_path, key, old, new}; - for stdout
+    #[arg(long, global = true, value_name = "PATH")]
+    changes: Option<PathBuf>,
+
+    /// When checking, print a unified diff of what update would write
+    /// to each file
+    #[arg(long, global = true, conflicts_with = "diff_tool")]
+    diff: bool,
+
+    /// When checking, show each file that would change with this diff
+    /// viewer, like "delta" or "difft", given the current and new files
+    #[arg(long, global = true, value_name = "CMD")]
+    diff_tool: Option<String>,
+
+    /// Also run in each workspace nested below this one, like one under
+    /// tools/ that it excludes; =same gives them the version worked out
+    /// here, instead of each working out its own
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "VERSIONS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "independent"
+    )]
+    recursive: Option<Recursion>,
+
+    /// The exit code to use when a check finds differences, instead of 1
+    #[arg(long, global = true, value_name = "CODE")]
+    exit_differences: Option<u8>,
+
+    /// The exit code to use when there are only warnings, instead of 0
+    #[arg(long, global = true, value_name = "CODE")]
+    exit_warnings: Option<u8>,
+
+    /// The exit code to use when something goes wrong, instead of 2
+    #[arg(long, global = true, value_name = "CODE")]
+    exit_error: Option<u8>,
+
+    /// every difference found, for reports made at the end
+    #[arg(skip)]
+    report: report::Report,
+
+    /// how to format the values we write, from the configuration
+    #[arg(skip)]
+    value_style: Cell<config::ValueStyle>,
+
+    /// the version worked out for the top workspace, which the nested
+    /// ones are given with --recursive=same
+    #[arg(skip)]
+    shared_version: RefCell<Option<String>>,
 }
 
 impl Args {
-    /// Get the version without any leading 'v'
-    fn version(&self) -> &str {
-        let ver = self.cmd.version();
-        if let Some(ver) = ver.strip_prefix('v') {
-            ver
+    fn write(&self) -> bool {
+        matches!(
+            self.cmd,
+            SubCommand::Update { dry_run: false, .. }
+                | SubCommand::Bump { .. }
+                | SubCommand::DepUpdate { .. }
+                | SubCommand::Promote
+                | SubCommand::Dev
+                | SubCommand::Init
+        )
+    }
+    /// Whether version requirements on members with a path are removed,
+    /// instead of being kept up to date
+    fn removes_dep_versions(&self) -> bool {
+        self.strip_dep_versions || matches!(self.cmd, SubCommand::Dev)
+    }
+    /// Keep the version worked out for the top workspace, for the
+    /// nested ones with --recursive=same
+    fn share(&self, version: &str) {
+        let mut shared = self.shared_version.borrow_mut();
+        if self.recursive == Some(Recursion::Same) && shared.is_none() {
+            *shared = Some(version.to_string());
+        }
+    }
+    fn check(&self) -> bool {
+        matches!(self.cmd, SubCommand::Check { .. })
+    }
+    /// Whether this is an update that only shows what it would do
+    fn dry_run(&self) -> bool {
+        matches!(self.cmd, SubCommand::Update { dry_run: true, .. })
+    }
+    fn report_format(&self) -> ReportFormat {
+        match self.cmd {
+            SubCommand::Update { format, .. } | SubCommand::Check { format, .. } => format,
+            _ => ReportFormat::Text,
+        }
+    }
+    /// Report a value that doesn't have what we want
+    ///
+    /// * `change` - what has to change
+    /// * `message` - what to print, which is followed by "(fixing)" if
+    ///   the value is going to be changed
+    fn difference(&self, change: report::Change, message: String) {
+        tracing::info!("{message}");
+        if !self.quiet {
+            let (paint, fixing) = if self.write() {
+                (Paint::Fixed, " (fixing)")
+            } else {
+                (Paint::Wrong, "")
+            };
+            let line = format!("{message}{fixing}");
+            self.println(&paint.apply(&line, self.color.enabled()));
+        }
+        self.report.add(change, message);
+    }
+    /// Print the reasoning behind a decision, if --explain or -vv was
+    /// given
+    fn explain<D: std::fmt::Display>(&self, why: D) {
+        self.log(2, why);
+    }
+    /// Log something that is only of interest with -v, or more of
+    /// them, or with --explain, as a debug event for one -v and a trace
+    /// event for more
+    ///
+    /// * `level` - how many -v it takes
+    /// * `what` - what to log
+    fn log<D: std::fmt::Display>(&self, level: u8, what: D) {
+        if level > 1 {
+            tracing::trace!("{what}");
         } else {
-            ver
+            tracing::debug!("{what}");
         }
     }
-    fn write(&self) -> bool {
-        matches!(self.cmd, SubCommand::Update { newver: _ })
+    /// Print a line, moving the progress bar out of the way
+    fn println(&self, line: &str) {
+        progress::around(|| println!("{line}"));
     }
-    fn check(&self) -> bool {
-        matches!(self.cmd, SubCommand::Check { newver: _ })
-    }
-}
-
-fn main() -> Result<(), Error> {
-    let cli = Args::parse();
-
-    // first read the top level Cargo.cli
-    let base = std::fs::read_to_string("Cargo.toml")?;
-    let doc = base.parse::<Document>()?;
-    // get the [workspace] section
-    let workspace = doc
-        .get("workspace")
-        .ok_or(anyhow!("No [workspace] section in top level"))?;
-    // find the members array inside the workspace
-    let members = workspace
-        .get("members")
-        .ok_or(anyhow!("No members in [workspace] section"))?
-        .as_array()
-        .ok_or(anyhow!("members must be an array"))?;
-
-    // save these members into a hashmap for easy lookup later. We will
-    // only change [dependencies] that point to one of these, and we need
-    // to check each one to see if it's one we care about
-    let members_lookup = members
-        .iter()
-        .map(|v| v.as_str().expect("member wasn't a string").to_string())
-        .collect::<HashSet<String>>();
+}
+
+fn main() -> ExitCode {
+    let mut cli = Args::parse();
+    // a report is printed at the end, so nothing else can go to stdout
+    if cli.report_format() != ReportFormat::Text || cli.porcelain {
+        cli.quiet = true;
+    }
+    trace::init(cli.verbose, cli.explain, cli.quiet, cli.color.enabled());
+    let outcome = enter_workspace(&mut cli).and_then(|()| {
+        if cli.recursive.is_some() {
+            run_recursive(&cli)
+        } else {
+            run(&cli)
+        }
+    });
+    // the configuration is read again here, since we might have failed
+    // before the workspace was loaded
+    let configured = fs::read_to_string("Cargo.toml")
+        .ok()
+        .and_then(|s| encoding::without_bom(&s).parse::<Document>().ok())
+        .and_then(|doc| Config::from_document(&doc).ok())
+        .map(|config| config.exit_codes)
+        .unwrap_or_default();
+    let code = match outcome {
+        Ok(Outcome::Clean) => 0,
+        Ok(Outcome::Warnings) => cli
+            .exit_warnings
+            .or(configured.warnings)
+            .unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            if e.is::<report::DifferencesFound>() {
+                cli.exit_differences.or(configured.differences).unwrap_or(1)
+            } else {
+                cli.exit_error.or(configured.error).unwrap_or(2)
+            }
+        }
+    };
+    ExitCode::from(code)
+}
+
+/// Read a --report, which is a format and a file: junit=report.xml
+fn parse_report(arg: &str) -> Result<(ReportFormat, PathBuf), String> {
+    let (format, path) = arg
+        .split_once('=')
+        .ok_or("expected FORMAT=PATH, like junit=report.xml")?;
+    let format = ReportFormat::from_str(format, true)?;
+    if format == ReportFormat::Text || path.is_empty() {
+        return Err(format!("{arg} needs a report format and a file"));
+    }
+    Ok((format, PathBuf::from(path)))
+}
+
+/// Go to the top of the workspace given with --manifest-path, or the
+/// one we were run in from a subdirectory, since everything else works
+/// from the current directory
+///
+/// Paths to our own files, like the one for --changes, are made
+/// absolute first, so they still mean what they did
+///
+/// * `cli` - the command line arguments passed in
+fn enter_workspace(cli: &mut Args) -> Result<(), Error> {
+    let dir = match &cli.manifest_path {
+        Some(manifest) => {
+            if manifest.file_name() != Some("Cargo.toml".as_ref()) {
+                bail!(
+                    "--manifest-path must be the path of a Cargo.toml, not {}",
+                    manifest.display()
+                );
+            }
+            manifest
+                .parent()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .unwrap_or(Path::new("."))
+                .to_path_buf()
+        }
+        None => {
+            // without one, whatever fails to load is reported as usual
+            let Some(dir) = workspace::find_root() else {
+                return Ok(());
+            };
+            if !cli.quiet {
+                println!("Using the workspace in {}", dir.display());
+            }
+            dir
+        }
+    };
+    // other paths we were given are still from where we were run
+    let here = std::env::current_dir().context("Can't find the current directory")?;
+    if let Some(path) = cli.changes.as_mut().filter(|p| *p != Path::new("-")) {
+        *path = here.join(&path);
+    }
+    for (_, path) in &mut cli.reports {
+        *path = here.join(&path);
+    }
+    if let SubCommand::Update { newver, .. } | SubCommand::Check { newver, .. } = &mut cli.cmd {
+        if let Some(path) = &mut newver.from_file {
+            *path = here.join(&path);
+        }
+    }
+    std::env::set_current_dir(&dir).context(format!("Can't go into {}", dir.display()))
+}
+
+/// Run the command in this workspace, and then in each workspace
+/// nested below it
+///
+/// Each error is printed as it happens, and the others still run unless
+/// the nested ones need the version from the top one
+///
+/// * `cli` - the command line arguments passed in
+fn run_recursive(cli: &Args) -> Result<Outcome, Error> {
+    let root = std::env::current_dir().context("Can't find the current directory")?;
+    let mut outcome = Outcome::Clean;
+    let mut failed = vec![];
+    let mut all_differences = true;
+    for dir in std::iter::once(PathBuf::from(".")).chain(workspace::nested(Path::new("."))) {
+        if dir != Path::new(".") {
+            if !cli.quiet {
+                println!("\nWorkspace in {}:", dir.display());
+            }
+            std::env::set_current_dir(root.join(&dir))
+                .context(format!("Can't go into {}", dir.display()))?;
+        }
+        cli.report.clear();
+        let result = run(cli);
+        std::env::set_current_dir(&root).context("Can't go back to the top workspace")?;
+        match result {
+            Ok(Outcome::Clean) => {}
+            Ok(Outcome::Warnings) => outcome = Outcome::Warnings,
+            Err(e) if dir == Path::new(".") && cli.recursive == Some(Recursion::Same) => {
+                return Err(e)
+            }
+            Err(e) => {
+                eprintln!("Error in {}: {e:?}", dir.display());
+                all_differences &= e.is::<report::DifferencesFound>();
+                failed.push(dir.display().to_string());
+            }
+        }
+    }
+    if failed.is_empty() {
+        return Ok(outcome);
+    }
+    let message = format!("Failed in the workspaces in {}", failed.join(", "));
+    if all_differences {
+        Err(report::DifferencesFound(message).into())
+    } else {
+        Err(anyhow!(message))
+    }
+}
+
+/// Run the command we were given
+///
+/// * `cli` - the command line arguments passed in
+fn run(cli: &Args) -> Result<Outcome, Error> {
+    // doctor has to work even when the workspace can't be loaded
+    if let SubCommand::Doctor = cli.cmd {
+        return doctor::run(cli.quiet).map(|warnings| {
+            if warnings {
+                Outcome::Warnings
+            } else {
+                Outcome::Clean
+            }
+        });
+    }
+    if let SubCommand::Config {
+        action: ConfigAction::Schema,
+    } = cli.cmd
+    {
+        println!("{:#}", config::schema());
+        return Ok(Outcome::Clean);
+    }
+    // both sides are loaded from elsewhere, so the current directory
+    // doesn't have to be a workspace
+    if let SubCommand::Compare {
+        old,
+        new,
+        changed,
+        format,
+    } = &cli.cmd
+    {
+        return compare::run(old, new, *changed, *format).map(|()| Outcome::Clean);
+    }
+    // hold the lock from before we read anything until after the last
+    // write, so another run can't change a manifest in between
+    let _lock = if cli.write() {
+        Some(lock::acquire(cli.quiet)?)
+    } else {
+        None
+    };
+    let mut ws = if cli.cargo_metadata {
+        Workspace::load(&metadata::members(cli.default_members_only)?, false)?
+    } else {
+        Workspace::load(&cli.members, cli.default_members_only)?
+    };
+    cli.value_style.set(ws.config.value_style);
+    // update reports members it can't read along with everything else
+    let updates = matches!(
+        cli.cmd,
+        SubCommand::Update { .. }
+            | SubCommand::Check { .. }
+            | SubCommand::Bump { .. }
+            | SubCommand::Promote
+            | SubCommand::Dev
+    );
+    if !updates {
+        workspace::problems(std::mem::take(&mut ws.errors))?;
+    }
+
+    let metadata = cli.metadata.as_deref().map(target::metadata).transpose()?;
+    let shared = cli.shared_version.borrow().clone();
+    let finish = |newver: String| {
+        let newver = if let Some(shared) = &shared {
+            shared.clone()
+        } else if cli.strip_metadata {
+            strip_metadata(&newver).to_string()
+        } else if let Some(metadata) = &metadata {
+            // on top of any the version already has
+            let join = if newver.contains('+') { '.' } else { '+' };
+            format!("{newver}{join}{metadata}")
+        } else {
+            newver
+        };
+        cli.share(&newver);
+        newver
+    };
+    let result = match &cli.cmd {
+        SubCommand::Get { format } => get::print(&ws, *format),
+        SubCommand::List { format } => list::print(&ws, *format),
+        SubCommand::Status { offline, format } => {
+            status::print(&ws, *offline, cli.ignore_prerelease, *format)
+        }
+        SubCommand::Audit { format } => audit::run(&ws, cli.ignore_prerelease, *format, cli.quiet),
+        SubCommand::VerifyTags { format } => {
+            verify_tags::run(&ws, cli.ignore_prerelease, *format, cli.quiet)
+        }
+        SubCommand::Doctor | SubCommand::Config { .. } | SubCommand::Compare { .. } => {
+            unreachable!("handled before loading")
+        }
+        SubCommand::Update { newver, .. } => {
+            let newver = finish(newver.resolve(&ws, cli.scheme)?);
+            check_policies(&ws, &newver, cli)?;
+            let result = apply(&mut ws, &newver, cli);
+            let written = print_report(&ws, &newver, &result, cli);
+            result.and(written)
+        }
+        SubCommand::Check {
+            newver,
+            github_check,
+            pr_comment,
+            not_placeholder,
+            ..
+        } => {
+            if *not_placeholder {
+                dev::check_not_placeholder(&ws, cli.quiet)?;
+            }
+            let newver = if newver.given() {
+                let newver = finish(newver.resolve(&ws, cli.scheme)?);
+                check_policies(&ws, &newver, cli)?;
+                newver
+            } else {
+                // without a version, everything has to agree with the
+                // workspace, whatever version that is
+                let current = match &shared {
+                    Some(shared) => shared.as_str(),
+                    None => ws.current_version().ok_or(anyhow!(
+                        "The workspace has no version to check against; pass one"
+                    ))?,
+                };
+                cli.share(current);
+                if !cli.quiet {
+                    println!("Checking that everything has the workspace version {current}");
+                }
+                current.to_string()
+            };
+            let result = update(&mut ws, &newver, cli);
+            let differences = cli.report.differences();
+            if github::in_actions() && !cli.quiet {
+                print!("{}", github::workflow_annotations(&differences));
+            }
+            if *github_check {
+                github::check_run(&newver, &differences, result.as_ref().err(), cli.quiet)?;
+            }
+            if *pr_comment {
+                let body =
+                    markdown::render(&ws, &newver, false, &differences, result.as_ref().err());
+                if std::env::var_os("GITLAB_CI").is_some() {
+                    gitlab::mr_comment(&body, cli.quiet)?;
+                } else {
+                    github::pr_comment(&body, cli.quiet)?;
+                }
+            }
+            let written = print_report(&ws, &newver, &result, cli);
+            result.and(written)
+        }
+        SubCommand::Init => init::run(&mut ws, cli.quiet),
+        SubCommand::DepUpdate { git, reference } => {
+            dep_update::run(&mut ws, git, reference.as_deref(), cli)
+        }
+        SubCommand::Next { level, id } => {
+            let newver = match (level, cli.scheme) {
+                (Some(level), _) => level.next(&ws, id.as_deref())?,
+                (None, version::Scheme::Calver) => calver::next(ws.current_version())?,
+                (None, version::Scheme::Semver) => {
+                    bail!(
+                        "next needs a level, like patch or minor, unless --scheme calver is given"
+                    )
+                }
+            };
+            println!("{}", finish(newver));
+            Ok(())
+        }
+        SubCommand::Bump { level, id } => {
+            let newver = finish(level.next(&ws, id.as_deref())?);
+            check_policies(&ws, &newver, cli)?;
+            if !cli.quiet {
+                let old = ws.current_version().unwrap_or_default();
+                println!("Bumping {old} to {newver}");
+            }
+            apply(&mut ws, &newver, cli)
+        }
+        SubCommand::Promote => {
+            let newver = finish(target::promote(&ws)?);
+            check_policies(&ws, &newver, cli)?;
+            if !cli.quiet {
+                let old = ws.current_version().unwrap_or_default();
+                println!("Promoting {old} to {newver}");
+            }
+            apply(&mut ws, &newver, cli)
+        }
+        SubCommand::Dev => {
+            // no hooks or webhook, since this isn't a release
+            let placeholder = ws.config.placeholder().to_string();
+            if !cli.quiet {
+                println!("Setting the development placeholder {placeholder}");
+            }
+            update(&mut ws, &placeholder, cli)
+        }
+    };
+    if let Some(path) = &cli.changes {
+        let changes = format!("{:#}\n", cli.report.changes());
+        if path == Path::new("-") {
+            print!("{changes}");
+        } else {
+            fs::write(path, changes).context(format!("Can't write {}", path.display()))?;
+        }
+    }
+    result.map(|()| Outcome::Clean)
+}
+
+/// Check and/or update every member and extra file to the new version
+///
+/// * `ws` - the workspace to work on
+/// * `newver` - the version we want, without any leading 'v'
+/// * `opts` - the command line arguments passed in
+fn update(ws: &mut Workspace, newver: &str, opts: &Args) -> Result<(), Error> {
+    // save the package names of the members into a hashset for easy lookup
+    // later. We will only change [dependencies] that point to one of these,
+    // and we need to check each one to see if it's one we care about
+    // members we were told to skip keep their version, so requirements
+    // on them are left alone too
+    let mut members_lookup = ws.members_lookup();
+    members_lookup.retain(|name| !ws.config.skip.contains(name));
 
     let mut some_difference_found = false;
 
+    // the top level manifest, unless it is also listed as a member, in
+    // which case it is handled along with the other members
+    let root_is_member = ws.members.iter().any(|m| m.dir == ".");
+    if !root_is_member && !opts.no_root {
+        let _span = tracing::info_span!("manifest", path = %ws.manifest.display()).entered();
+        opts.report.examined(&ws.manifest);
+        let mut changed = !opts.deps_only && update_root(&mut ws.doc, &ws.manifest, newver, opts);
+        // requirements members inherit with `workspace = true`
+        if let Some(deps) = ws
+            .doc
+            .get_mut("workspace")
+            .and_then(Item::as_table_like_mut)
+            .and_then(|w| w.get_mut("dependencies"))
+            .filter(|_| !opts.no_deps)
+            .and_then(Item::as_table_like_mut)
+        {
+            changed |= update_deps(
+                &ws.manifest,
+                &["workspace", "dependencies"],
+                deps,
+                &members_lookup,
+                &ws.config,
+                newver,
+                opts,
+            );
+        }
+        if changed {
+            finish_file(&ws.manifest, ws.doc.to_string(), opts)?;
+            some_difference_found = true;
+        }
+    }
+
+    // members without a version can inherit one, if there is one
+    let inheritable = ws.package_version().is_some();
+    // a broken file is reported at the end, after looking at the rest
+    let mut errors = std::mem::take(&mut ws.errors);
+
     // work on each subdirectory (each member of the workspace)
-    for member in members {
-        // calculate the path of the inner member
-        let inner_path: PathBuf = [member.as_str().unwrap(), "Cargo.toml"].iter().collect();
-        // and load into a parsed yaml document
-        let inner = std::fs::read_to_string(&inner_path)
-            .context(format!("Can't read {}", inner_path.display()))?;
-        let mut inner = inner.parse::<Document>()?;
-
-        // now find the [package] section
-        let package = inner.get_mut("package").ok_or(anyhow!(format!(
+    progress::start(ws.members.len(), opts.progress && !opts.quiet);
+    for member in &mut ws.members {
+        progress::tick(&member.dir);
+        let is_root = member.dir == ".";
+        if (is_root && opts.no_root) || (!is_root && opts.root_only) {
+            continue;
+        }
+        if let Some(name) = member
+            .name()
+            .filter(|n| ws.config.skip.iter().any(|s| s == n))
+        {
+            opts.explain(format_args!(
+                "Skipped {}: {name} is listed in skip in [workspace.metadata.{}]",
+                member.manifest.display(),
+                config::TABLE
+            ));
+            continue;
+        }
+        match update_member(
+            member,
+            inheritable,
+            &members_lookup,
+            &ws.config,
+            newver,
+            opts,
+        ) {
+            Ok(changed) => some_difference_found |= changed,
+            Err(e) => errors.push(e),
+        }
+    }
+
+    progress::finish();
+
+    // files outside of cargo that also carry the version
+    let extras = if opts.root_only || opts.deps_only || matches!(opts.cmd, SubCommand::Dev) {
+        vec![]
+    } else {
+        ExtraFile::from_config(&ws.config)
+    };
+    for extra in extras {
+        let _span = tracing::info_span!("extra", path = %extra.path.display()).entered();
+        opts.report.examined(&extra.path);
+        let rewritten = std::fs::read_to_string(&extra.path)
+            .context(format!("Can't read {}", extra.path.display()))
+            .and_then(|contents| extra.rewrite(&contents, newver));
+        let (contents, edits) = match rewritten {
+            Ok(rewritten) => rewritten,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        for edit in &edits {
+            opts.difference(
+                report::Change {
+                    path: extra.path.clone(),
+                    toml_path: None,
+                    key: edit.what.clone(),
+                    old: edit.old.clone(),
+                    new: edit.new.clone(),
+                },
+                format!(
+                    "Version for {} in {} was {} want {}",
+                    edit.what,
+                    extra.path.display(),
+                    edit.old,
+                    edit.new,
+                ),
+            );
+            opts.explain(format_args!(
+                "  because {} is listed in {} in [workspace.metadata.{}]",
+                extra.path.display(),
+                extra.kind.config_key(),
+                config::TABLE,
+            ));
+        }
+        if !edits.is_empty() {
+            if let Err(e) = finish_file(&extra.path, contents, opts) {
+                errors.push(e);
+            }
+            some_difference_found = true;
+        }
+    }
+
+    if !opts.quiet {
+        print!("\n{}", opts.report.totals(ws, opts.write()));
+    }
+    workspace::problems(errors)?;
+
+    if opts.check() && some_difference_found {
+        return Err(report::DifferencesFound("There were differences".to_string()).into());
+    }
+    if opts.check() && !opts.quiet {
+        println!("All files had the correct version");
+    }
+    Ok(())
+}
+
+/// Check and/or update one member, returning whether it had anything
+/// to change
+///
+/// * `member` - the member to work on
+/// * `inheritable` - whether `[workspace.package]` has a version
+/// * `members` - the package names of the members, for finding
+///   dependencies on them
+/// * `config` - the settings from the top level manifest
+/// * `newver` - the version we want, without any leading 'v'
+/// * `opts` - the command line arguments passed in
+fn update_member(
+    member: &mut workspace::Member,
+    inheritable: bool,
+    members: &HashSet<String>,
+    config: &Config,
+    newver: &str,
+    opts: &Args,
+) -> Result<bool, Error> {
+    let is_root = member.dir == ".";
+    let inner_path = &member.manifest;
+    let inner = &mut member.doc;
+    let _span = tracing::info_span!("member", manifest = %inner_path.display()).entered();
+    opts.report.examined(inner_path);
+
+    // keep track of if we changed anything, to avoid unnecessary rewrites
+    let mut changed = is_root && !opts.deps_only && update_root(inner, inner_path, newver, opts);
+
+    // now find the [package] section
+    let package = inner
+        .get_mut("package")
+        .and_then(Item::as_table_like_mut)
+        .ok_or(anyhow!(format!(
             "no [package] section in {}",
             inner_path.display()
         )))?;
-        // which contains: version = "xxx"; mutable since we might change it
-        let version = package.get_mut("version");
+    if opts.set_missing && !opts.deps_only && !package.contains_key("version") {
+        changed |= add_package_version(package, inner_path, inheritable, newver, opts);
+    }
+    // which contains: version = "xxx"; mutable since we might change it
+    let version = package.get_mut("version");
 
-        // keep track of if we changed anything, to avoid unnecessary rewrites
-        let mut changed = false;
+    // extract the value; we want a better error here in case we can't find
+    // it or if the version couldn't be parsed as a string
+    match version {
+        // only the requirements were asked for
+        _ if opts.deps_only => {}
+        None => bail!(format!(
+            "No version in {}; pass --set-missing to add one",
+            inner_path.display()
+        )),
+        // version.workspace = true is handled by the top level manifest
+        Some(version) if workspace::is_inherited(version) => {
+            opts.explain(format_args!(
+                "Skipped [package].version of {}: it is inherited from [workspace.package]",
+                inner_path.display()
+            ));
+        }
+        Some(Item::Value(v)) => {
+            let source = inner_path.display().to_string();
+            if check_version(
+                v,
+                inner_path,
+                &["package", "version"],
+                "version",
+                source,
+                newver,
+                opts,
+            ) {
+                changed = true;
+                opts.explain("  because [package].version is checked in every member");
+            }
+        }
+        Some(_) => bail!(format!(
+            "version in {} wasn't a string",
+            inner_path.display()
+        )),
+    }
 
-        // extract the value; we want a better error here in case we can't find
-        // it or if the version couldn't be parsed as a string
-        match version {
-            None => {
-                // TODO: We could just set the version...
-                bail!(format!("No version in {}", inner_path.display()))
+    // now work on the dependency tables, including those for
+    // each target. We only care about dependencies on packages that
+    // are members of the workspace, which we check using the
+    // hashset of package names created earlier
+    if !opts.no_deps {
+        workspace::visit_dependency_tables(inner, |keys, deps| {
+            // only the top level manifest can have
+            // [workspace.dependencies]
+            if keys[0] == "workspace" && !is_root {
+                return;
             }
-            Some(Item::Value(v)) => {
-                changed |= check_version(v, inner_path.display().to_string(), &cli);
+            changed |= update_deps(inner_path, keys, deps, members, config, newver, opts);
+        });
+    }
+    if changed {
+        finish_file(inner_path, inner.to_string(), opts)?;
+    } else {
+        opts.log(1, format_args!("{} is up to date", inner_path.display()));
+    }
+    Ok(changed)
+}
+
+/// Update everything to a new version, running the hooks around it and
+/// then telling the webhook
+///
+/// * `ws` - the workspace to update
+/// * `newver` - the version we want, without any leading 'v'
+/// * `opts` - the command line arguments passed in
+fn apply(ws: &mut Workspace, newver: &str, opts: &Args) -> Result<(), Error> {
+    let old = ws.current_version().map(String::from);
+    let mut env = hooks::Env {
+        old: old.as_deref(),
+        new: newver,
+        dry_run: opts.dry_run(),
+        changed: vec![],
+    };
+    hooks::run(
+        "pre-update-hook",
+        &ws.config.pre_update_hook,
+        &env,
+        opts.quiet,
+    )?;
+    update(ws, newver, opts)?;
+    for difference in opts.report.differences() {
+        let path = report::display_path(&difference.change.path);
+        if !env.changed.contains(&path) {
+            env.changed.push(path);
+        }
+    }
+    let crates = report::crates(ws, &opts.report.differences());
+    let crates = crates
+        .iter()
+        .filter(|c| c.old.is_some())
+        .map(|c| c.name)
+        .collect::<Vec<_>>();
+    if !env.dry_run {
+        github::set_outputs(newver, &env.changed, &crates)?;
+    }
+    hooks::run(
+        "post-update-hook",
+        &ws.config.post_update_hook,
+        &env,
+        opts.quiet,
+    )
+    .context("Everything was updated, but the hook failed")?;
+    // the verify commands would only check the tree as it was
+    if env.dry_run {
+        if !opts.verify.is_empty() {
+            opts.log(1, "Not running --verify, since this is a dry run");
+        }
+        return Ok(());
+    }
+    for verify in &opts.verify {
+        let command = verify
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if command.is_empty() {
+            bail!("--verify needs a command");
+        }
+        hooks::run("verify", &command, &env, opts.quiet).context("Verifying the update failed")?;
+    }
+    notify(ws, old.as_deref(), newver, opts)
+}
+
+/// Print the report of a `check` or `update` in the format asked for,
+/// and write the ones asked for with --report
+///
+/// Text needs nothing here, since it is printed as we go
+///
+/// * `ws` - the workspace, as the update left it
+/// * `newver` - the version that was checked for or applied
+/// * `result` - how the check or update went
+/// * `opts` - the command line arguments, with the format
+fn print_report(
+    ws: &Workspace,
+    newver: &str,
+    result: &Result<(), Error>,
+    opts: &Args,
+) -> Result<(), Error> {
+    if opts.porcelain {
+        print!("{}", opts.report.porcelain());
+    } else {
+        print!(
+            "{}",
+            render_report(opts.report_format(), ws, newver, result, opts)
+        );
+    }
+    for (format, path) in &opts.reports {
+        let report = render_report(*format, ws, newver, result, opts);
+        fs::write(path, report).context(format!("Can't write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// The report of a `check` or `update` in one format
+///
+/// * `format` - the format
+/// * `ws` - the workspace, as the update left it
+/// * `newver` - the version that was checked for or applied
+/// * `result` - how the check or update went
+/// * `opts` - the command line arguments passed in
+fn render_report(
+    format: ReportFormat,
+    ws: &Workspace,
+    newver: &str,
+    result: &Result<(), Error>,
+    opts: &Args,
+) -> String {
+    let differences = opts.report.differences();
+    let error = result.as_ref().err();
+    let writing = opts.write();
+    match format {
+        ReportFormat::Text => String::new(),
+        ReportFormat::Html => html::render(ws, newver, writing, &differences, error),
+        ReportFormat::Markdown => markdown::render(ws, newver, writing, &differences, error),
+        ReportFormat::Sarif => sarif::render(newver, writing, &differences, error),
+        ReportFormat::Junit => junit::render(ws, newver, writing, &differences, error),
+        ReportFormat::Json => format!("{:#}\n", opts.report.json(newver, writing, error)),
+        ReportFormat::CargoJson => diagnostics::render(ws, writing, &differences, error),
+    }
+}
+
+/// Check the new version against the rules given on the command line,
+/// before anything is changed
+///
+/// * `ws` - the workspace being updated
+/// * `newver` - the version about to be applied
+/// * `opts` - the command line arguments, with the rules
+fn check_policies(ws: &Workspace, newver: &str, opts: &Args) -> Result<(), Error> {
+    if !opts.allow_non_semver {
+        newver.parse::<version::Version>().context(format!(
+            "{newver:?} isn't a semantic version, which cargo needs; \
+             pass --allow-non-semver to use it anyway"
+        ))?;
+    }
+    // a dry run has to refuse whatever the update itself would
+    if (opts.write() || opts.dry_run()) && !opts.allow_downgrade {
+        policy::not_downgrade(ws, newver)?;
+    }
+    policy::allowed(&ws.config, newver)?;
+    policy::branch_allowed(&ws.config, newver)?;
+    if opts.scheme == version::Scheme::Calver {
+        calver::check(newver)?;
+    }
+    if let Some(level) = opts.require_semver_increase {
+        policy::require_increase(
+            ws,
+            newver,
+            level,
+            opts.baseline.as_deref(),
+            opts.ignore_prerelease,
+        )?;
+    }
+    Ok(())
+}
+
+/// Tell the configured webhook about an update, if anything changed
+///
+/// * `ws` - the workspace that was updated
+/// * `old` - the version before the update
+/// * `newver` - the version after it
+/// * `opts` - the command line arguments passed in
+fn notify(ws: &Workspace, old: Option<&str>, newver: &str, opts: &Args) -> Result<(), Error> {
+    if opts.report.is_empty() {
+        return Ok(());
+    }
+    let crates = ws
+        .members
+        .iter()
+        .filter_map(|m| m.name())
+        .filter(|name| !ws.config.skip.iter().any(|s| s == name))
+        .collect::<Vec<_>>();
+    notify::webhook(&ws.config, old, newver, &crates, opts.quiet)
+        .context("Everything was updated, but the webhook failed")
+}
+
+/// Check and/or update the versions in the top level manifest
+///
+/// This is `[workspace.package].version`, which members inherit with
+/// `version.workspace = true`
+///
+/// * `doc` - the top level manifest
+/// * `path` - where the top level manifest is
+/// * `newver` - the version we want
+/// * `opts` - the command line arguments passed in
+///
+/// Returns true if any changes were made
+fn update_root(doc: &mut Document, path: &Path, newver: &str, opts: &Args) -> bool {
+    let version = doc
+        .get_mut("workspace")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|w| w.get_mut("package"))
+        .and_then(Item::as_table_like_mut)
+        .and_then(|p| p.get_mut("version"));
+    if let Some(Item::Value(v)) = version {
+        let source = format!("[workspace.package] in {}", path.display());
+        if check_version(
+            v,
+            path,
+            &["workspace", "package", "version"],
+            "version",
+            source,
+            newver,
+            opts,
+        ) {
+            opts.explain("  because members can inherit [workspace.package].version");
+            return true;
+        }
+    }
+    false
+}
+
+/// Report a file that had the wrong version, and write it if updating
+///
+/// * `path` - the file that was changed
+/// * `contents` - the new contents, with the right version
+/// * `opts` - the command line arguments passed in
+fn finish_file(path: &Path, contents: String, opts: &Args) -> Result<(), Error> {
+    let contents = encoding::like_file(path, contents);
+    let paint = if opts.write() {
+        Paint::Fixed
+    } else {
+        Paint::Wrong
+    };
+    let colored = opts.color.enabled();
+    let say = |what: &str| {
+        if !opts.quiet {
+            let line = format!("{} {what}", path.display());
+            opts.println(&paint.apply(&line, colored));
+        }
+    };
+    say(if opts.write() {
+        "was updated"
+    } else {
+        "has the wrong version"
+    });
+    tracing::info!(
+        "{} {}",
+        path.display(),
+        if opts.write() {
+            "written"
+        } else {
+            "needs to be updated"
+        }
+    );
+    if opts.write() {
+        say("was updated");
+        atomic::write(path, contents).context(format!("Can't write {}", path.display()))?;
+    } else {
+        say("needs to be updated");
+        if (opts.diff || opts.dry_run()) && !opts.quiet {
+            let current =
+                fs::read_to_string(path).context(format!("Can't read {}", path.display()))?;
+            let diff = diff::unified(path, &current, &contents);
+            if !diff.is_empty() {
+                opts.println(color::diff(&diff, colored).trim_end());
             }
-            Some(_) => bail!(format!(
-                "version in {} wasn't a string",
-                inner_path.display()
-            )),
-        }
-
-        // now work on the [dependencies] section. We only care about
-        // dependencies with names that are one of the subdirectories
-        // we found when we parsed the members section at the top level
-        // so we filter using the hashset created earlier
-        // dependencies consist of a table of "name = { inline_table }"
-        // entries. We skip those that don't have that format (the short
-        // form of "name = version" for example)
-        if let Some(deps) = inner.get_mut("dependencies") {
-            if let Some(deps) = deps.as_table_mut() {
-                // build an iterator of K,V pairs for each dependency
-                // and do the filtering here for items in the members_lookup
-                for dep in deps
-                    .iter_mut()
-                    .filter(|dep| members_lookup.contains(dep.0.get()))
-                {
-                    // call fixup_version for this dependency, which
-                    // might make a change if the version was wrong
-                    if let Some(inline_table) = dep.1.as_inline_table_mut() {
-                        changed |= update_dep_ver(&dep.0, inline_table, &cli);
-                    }
-                }
+        }
+        if let Some(tool) = &opts.diff_tool {
+            diff_tool::show(tool, path, &contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Check and/or update the requirements in a table of dependencies
+///
+/// Only dependencies on packages that are members of the workspace are
+/// looked at. A dependency may have been renamed, in which case the
+/// package it refers to is in its `package` key. Dependencies are
+/// expected to be either "name = version" or "name = { inline_table }"
+/// entries; we skip those that don't have either format
+///
+/// * `path` - the manifest the table is in
+/// * `keys` - the keys leading to the table, like `["dev-dependencies"]`
+/// * `deps` - the table of dependencies
+/// * `members` - the package names of the members to update
+///   requirements on
+/// * `config` - the settings, for the requirement style
+/// * `newver` - the version we want
+/// * `opts` - the command line arguments passed in
+///
+/// Returns true if any changes were made
+fn update_deps(
+    path: &Path,
+    keys: &[&str],
+    deps: &mut dyn TableLike,
+    members: &HashSet<String>,
+    config: &Config,
+    newver: &str,
+    opts: &Args,
+) -> bool {
+    let table = format!("[{}]", report::toml_path(keys));
+    let mut changed = false;
+    for (key, dep) in deps.iter_mut() {
+        let _span = tracing::info_span!("dependency", table = %table, name = %key.get()).entered();
+        if !opts.explain {
+            opts.log(
+                2,
+                format_args!(
+                    "Looking at dependency {} in {table} of {}",
+                    key.get(),
+                    path.display()
+                ),
+            );
+        }
+        // the requirement is in [workspace.dependencies], which is
+        // checked along with the top level manifest
+        if workspace::is_inherited(dep) {
+            opts.explain(format_args!(
+                "Skipped dependency {} in {table} of {}: it is inherited from [workspace.dependencies]",
+                key.get(),
+                path.display()
+            ));
+            continue;
+        }
+        let package = workspace::dependency_package(key.get(), dep).to_string();
+        if !members.contains(&package) {
+            let why = if config.skip.contains(&package) {
+                format!("{package} is listed in skip")
+            } else {
+                format!("{package} isn't the package name of any member")
             };
+            opts.explain(format_args!(
+                "Skipped dependency {} in {table} of {}: {why}",
+                key.get(),
+                path.display()
+            ));
+            continue;
         }
-        if changed {
-            if !cli.quiet {
-                println!(
-                    "{} {}",
-                    inner_path.display(),
-                    if cli.write() {
-                        "was updated"
-                    } else {
-                        "has the wrong version"
+        let style = config.style_for(&package);
+        // cargo ignores build metadata in requirements,
+        // and warns about it
+        let want =
+            |existing: Option<&str>| config.requirement(&package, strip_metadata(newver), existing);
+        let inline = dep.is_inline_table();
+        let updated = if let Some(v) = dep.as_value_mut().filter(|v| v.is_str()) {
+            // the short form, "name = version"
+            let want = want(v.as_str());
+            if compatible(path, &table, key.get(), v.as_str(), &want, newver, opts) {
+                continue;
+            }
+            let toml_path = [keys, &[key.get()]].concat();
+            let source = format!("dependency for {}", key.get());
+            check_version(v, path, &toml_path, key.get(), source, &want, opts)
+        } else if let Some(entry) = dep.as_table_like_mut() {
+            // an inline table, or a [dependencies.name] table
+            let git = entry.contains_key("git");
+            if git && opts.git_tags && update_dep_tag(path, keys, &key, entry, newver, opts) {
+                changed = true;
+                if inline && opts.value_style.get().normalize_inline_tables {
+                    entry.fmt();
+                }
+                opts.explain(format_args!(
+                    "  because {package} is a member of the workspace and --git-tags was given",
+                ));
+            }
+            // a git dependency pinned by tag usually has no version
+            if git && !entry.contains_key("version") {
+                continue;
+            }
+            // dev-dependencies with only a path are left out when
+            // publishing, so they don't need one
+            let publishable = keys.last() != Some(&"dev-dependencies");
+            if !entry.contains_key("version")
+                && entry.contains_key("path")
+                && publishable
+                && opts.add_missing_dep_version
+                && !opts.removes_dep_versions()
+            {
+                if add_dep_ver(path, keys, &key, entry, &want(None), opts) {
+                    changed = true;
+                    if inline && opts.value_style.get().normalize_inline_tables {
+                        entry.fmt();
                     }
-                );
+                    opts.explain(format_args!(
+                        "  because {package} is a member of the workspace and --add-missing-dep-version was given",
+                    ));
+                }
+                continue;
             }
-            if cli.write() {
-                if !cli.quiet {
-                    println!("{} was updated", inner_path.display());
+            if !entry.contains_key("version") {
+                opts.explain(format_args!(
+                    "Skipped dependency {} in {table} of {}: it has no version requirement",
+                    key.get(),
+                    path.display()
+                ));
+                continue;
+            }
+            if opts.removes_dep_versions() && entry.contains_key("path") {
+                if strip_dep_ver(path, keys, &key, entry, opts) {
+                    changed = true;
+                    opts.explain(format_args!(
+                        "  because {package} is a member of the workspace, and has a path",
+                    ));
                 }
-                fs::write(inner_path, inner.to_string())?;
-            } else if !cli.quiet {
-                println!("{} needs to be updated", inner_path.display());
+                continue;
+            }
+            let requirement = entry.get("version").and_then(Item::as_str);
+            let want = want(requirement);
+            if compatible(path, &table, key.get(), requirement, &want, newver, opts) {
+                continue;
+            }
+            // call fixup_version for this dependency, which
+            // might make a change if the version was wrong
+            let updated = update_dep_ver(path, keys, &key, entry, &want, opts);
+            if updated && inline && opts.value_style.get().normalize_inline_tables {
+                entry.fmt();
+            }
+            updated
+        } else {
+            opts.explain(format_args!(
+                "Skipped dependency {} in {table} of {}: only versions and tables like {{ path = ..., version = ... }} are updated",
+                key.get(),
+                path.display()
+            ));
+            continue;
+        };
+        if updated {
+            changed = true;
+            opts.explain(format_args!(
+                "  because {package} is a member of the workspace and is listed in {table} of {}",
+                path.display()
+            ));
+            if let Some(style) = style {
+                opts.explain(format_args!(
+                    "  and requirements on {package} are written in the {} style",
+                    style.name()
+                ));
             }
         }
-        some_difference_found |= changed;
     }
-    if cli.check() && some_difference_found {
-        bail!("There were differences")
+    changed
+}
+
+/// Check if a requirement should be left alone because it already
+/// allows the new version, unless `--exact-requirements` was given
+///
+/// A requirement that isn't written in the configured style still has
+/// to be rewritten, so `requirement-style` is enforced either way
+///
+/// * `path` - the manifest the dependency is in
+/// * `table` - the table the dependency is in, for explaining
+/// * `key` - the name of this dependency
+/// * `requirement` - the requirement, if it is a string
+/// * `want` - the requirement we would write, in the configured style
+/// * `newver` - the version we want
+/// * `opts` - the command line arguments passed in
+fn compatible(
+    path: &Path,
+    table: &str,
+    key: &str,
+    requirement: Option<&str>,
+    want: &str,
+    newver: &str,
+    opts: &Args,
+) -> bool {
+    if opts.exact_requirements {
+        return false;
     }
-    if cli.check() && !cli.quiet {
-        println!("All files had the correct version");
+    let (Some(requirement), Ok(version)) = (requirement, strip_metadata(newver).parse()) else {
+        return false;
+    };
+    if !version::satisfies(requirement, &version).unwrap_or_default()
+        || config::operator(requirement) != config::operator(want)
+    {
+        return false;
     }
-    Ok(())
+    opts.explain(format_args!(
+        "Skipped dependency {key} in {table} of {}: {requirement} already allows {version}",
+        path.display()
+    ));
+    true
 }
 
 /// Verify and/or update the version of a dependency
@@ -179,14 +1729,229 @@ fn main() -> Result<(), Error> {
 /// "version" attribute and make sure it matches what we expect
 /// from the command line arguments
 ///
+/// * `path` - the manifest the dependency is in
+/// * `keys` - the keys leading to the table the dependency is in
+/// * `key` - the name of this dependency
+/// * `dep` - the table of K/V pairs describing the dependency
+/// * `want` - the requirement we want, in the configured style
+/// * `opts` - the command line arguments passed in
+///
+/// Returns true if any changes were made
+fn update_dep_ver(
+    path: &Path,
+    keys: &[&str],
+    key: &KeyMut<'_>,
+    dep: &mut dyn TableLike,
+    want: &str,
+    opts: &Args,
+) -> bool {
+    let Some(v) = dep.get_mut("version").and_then(Item::as_value_mut) else {
+        return false;
+    };
+    let source = format!("dependency for {}", key.get());
+    let toml_path = [keys, &[key.get(), "version"]].concat();
+    check_version(v, path, &toml_path, key.get(), source, want, opts)
+}
+
+/// Add a version requirement to a dependency that only has a path
+///
+/// * `path` - the manifest the dependency is in
+/// * `keys` - the keys leading to the table the dependency is in
+/// * `key` - the name of this dependency
+/// * `dep` - the table of K/V pairs describing the dependency
+/// * `want` - the requirement we want, in the configured style
+/// * `opts` - the command line arguments passed in
+///
+/// Returns true if any changes were made
+fn add_dep_ver(
+    path: &Path,
+    keys: &[&str],
+    key: &KeyMut<'_>,
+    dep: &mut dyn TableLike,
+    want: &str,
+    opts: &Args,
+) -> bool {
+    opts.difference(
+        report::Change {
+            path: path.to_path_buf(),
+            toml_path: Some(report::toml_path(&[keys, &[key.get(), "version"]].concat())),
+            key: key.get().to_string(),
+            old: String::new(),
+            new: want.to_string(),
+        },
+        format!("Dependency for {} has no version, want {want}", key.get()),
+    );
+    let mut version = opts.value_style.get().string(&Value::from(want), want);
+    // the space before the closing brace of an inline table goes after
+    // the new last value; a comment stays where it is
+    let last = dep.iter_mut().last().and_then(|(_, v)| v.as_value_mut());
+    if let Some(last) = last {
+        let space = last
+            .decor()
+            .suffix()
+            .and_then(|s| s.as_str())
+            .filter(|s| s.chars().all(char::is_whitespace))
+            .map(String::from);
+        if let Some(space) = space {
+            last.decor_mut().set_suffix("");
+            version.decor_mut().set_prefix(" ");
+            version.decor_mut().set_suffix(space);
+        }
+    }
+    dep.insert("version", Item::Value(version));
+    true
+}
+
+/// Add a version to a member that has none, for --set-missing
+///
+/// The member inherits the version with `version.workspace = true` if
+/// `[workspace.package]` has one, or else gets the version itself
+///
+/// * `package` - the `[package]` table of the member
+/// * `path` - the member's manifest
+/// * `inherit` - whether there is a version to inherit
+/// * `newver` - the version we want
+/// * `opts` - the command line arguments passed in
+fn add_package_version(
+    package: &mut dyn TableLike,
+    path: &Path,
+    inherit: bool,
+    newver: &str,
+    opts: &Args,
+) -> bool {
+    let want = if inherit {
+        "version.workspace = true"
+    } else {
+        newver
+    };
+    opts.difference(
+        report::Change {
+            path: path.to_path_buf(),
+            toml_path: Some(report::toml_path(&["package", "version"])),
+            key: "version".to_string(),
+            old: String::new(),
+            new: newver.to_string(),
+        },
+        format!("No version in {}, want {want}", path.display()),
+    );
+    let version = if inherit {
+        let mut table = InlineTable::new();
+        table.insert("workspace", true.into());
+        table.set_dotted(true);
+        Value::InlineTable(table)
+    } else {
+        opts.value_style.get().string(&Value::from(newver), newver)
+    };
+    package.insert("version", Item::Value(version));
+    true
+}
+
+/// Remove the version requirement from a dependency with a path
+///
+/// * `path` - the manifest the dependency is in
+/// * `keys` - the keys leading to the table the dependency is in
 /// * `key` - the name of this dependency
 /// * `dep` - the table of K/V pairs describing the dependency
 /// * `opts` - the command line arguments passed in
 ///
 /// Returns true if any changes were made
-fn update_dep_ver(key: &KeyMut<'_>, dep: &mut InlineTable, opts: &Args) -> bool {
-    let v = dep.get_mut("version").unwrap();
-    check_version(v, format!("dependency for {}", key.get()), opts)
+fn strip_dep_ver(
+    path: &Path,
+    keys: &[&str],
+    key: &KeyMut<'_>,
+    dep: &mut dyn TableLike,
+    opts: &Args,
+) -> bool {
+    let Some(old) = dep.get("version") else {
+        return false;
+    };
+    let old = old.as_str().map_or_else(|| old.to_string(), String::from);
+    opts.difference(
+        report::Change {
+            path: path.to_path_buf(),
+            toml_path: Some(report::toml_path(&[keys, &[key.get(), "version"]].concat())),
+            key: key.get().to_string(),
+            old: old.clone(),
+            new: String::new(),
+        },
+        format!(
+            "Dependency for {} has version {old}, which is removed since it has a path",
+            key.get()
+        ),
+    );
+    // the document is changed either way, so a diff shows the removal;
+    // whether it is written is up to the caller
+    let last = dep.iter().last().is_some_and(|(k, _)| k == "version");
+    let removed = dep.remove("version");
+    // the space before the closing brace of an inline table went with it
+    let last_value = dep.iter_mut().last().and_then(|(_, v)| v.as_value_mut());
+    if let (true, Some(removed), Some(value)) =
+        (last, removed.as_ref().and_then(Item::as_value), last_value)
+    {
+        if let Some(suffix) = removed.decor().suffix() {
+            value.decor_mut().set_suffix(suffix.clone());
+        }
+    }
+    true
+}
+
+/// Verify and/or update the tag of a git dependency
+///
+/// The new tag keeps whatever came before the version in the old one,
+/// so `v1.0.0` becomes `v1.1.0` and `mycrate-1.0.0` becomes
+/// `mycrate-1.1.0`. Tags that don't end in a version are left alone
+///
+/// * `path` - the manifest the dependency is in
+/// * `keys` - the keys leading to the table the dependency is in
+/// * `key` - the name of this dependency
+/// * `dep` - the table of K/V pairs describing the dependency
+/// * `newver` - the version we want
+/// * `opts` - the command line arguments passed in
+///
+/// Returns true if any changes were made
+fn update_dep_tag(
+    path: &Path,
+    keys: &[&str],
+    key: &KeyMut<'_>,
+    dep: &mut dyn TableLike,
+    newver: &str,
+    opts: &Args,
+) -> bool {
+    let Some(tag) = dep.get_mut("tag").and_then(Item::as_value_mut) else {
+        return false;
+    };
+    let Some(old) = tag.as_str() else {
+        return false;
+    };
+    // keep what comes before the version; the rest of the tag must parse
+    let Some((prefix, _)) = version::split_tag(old) else {
+        opts.explain(format_args!(
+            "Skipped tag {old} of dependency {}: it doesn't end in a version",
+            key.get()
+        ));
+        return false;
+    };
+    let new = format!("{prefix}{newver}");
+    if old == new {
+        return false;
+    }
+    opts.difference(
+        report::Change {
+            path: path.to_path_buf(),
+            toml_path: Some(report::toml_path(&[keys, &[key.get(), "tag"]].concat())),
+            key: key.get().to_string(),
+            old: old.to_string(),
+            new: new.clone(),
+        },
+        format!("Tag for dependency for {} was {old} want {new}", key.get()),
+    );
+    *tag = opts.value_style.get().string(tag, &new);
+    true
+}
+
+/// Remove any `+build` metadata from a version
+fn strip_metadata(version: &str) -> &str {
+    version.split_once('+').map_or(version, |(v, _)| v)
 }
 
 /// Check and/or set the version
@@ -197,24 +1962,111 @@ fn update_dep_ver(key: &KeyMut<'_>, dep: &mut InlineTable, opts: &Args) -> bool
 /// Arguments:
 ///
 /// * `v` - the version to verify/change
+/// * `path` - the file the version is in
+/// * `toml_path` - the keys leading to the version in the file
+/// * `key` - the name of the value, for finding its line
 /// * `source` - the text of where this version came from
+/// * `newver` - the version we want
 /// * `opts` - the command line arguments
 ///
 /// Returns `true` if a change was made, `false` otherwise
-fn check_version<S: AsRef<str>>(v: &mut Value, source: S, opts: &Args) -> bool {
+fn check_version<S: AsRef<str>>(
+    v: &mut Value,
+    path: &Path,
+    toml_path: &[&str],
+    key: &str,
+    source: S,
+    newver: &str,
+    opts: &Args,
+) -> bool {
     if let Some(old) = v.as_str() {
-        if old != opts.version() {
-            if !opts.quiet {
-                println!(
-                    "Version for {} was {old} want {}{}",
-                    source.as_ref(),
-                    opts.version(),
-                    if opts.write() { " (fixing)" } else { "" },
-                );
-            }
-            *v = Value::String(Formatted::new(opts.version().to_string()));
+        // when checking, build metadata stamped on by CI doesn't count as a
+        // difference; when updating, it is removed
+        let compared = if opts.strip_metadata && opts.check() {
+            strip_metadata(old)
+        } else {
+            old
+        };
+        if compared == newver {
+            opts.report.unchanged(report::Change {
+                path: path.to_path_buf(),
+                toml_path: Some(report::toml_path(toml_path)),
+                key: key.to_string(),
+                old: old.to_string(),
+                new: old.to_string(),
+            });
+        } else {
+            opts.difference(
+                report::Change {
+                    path: path.to_path_buf(),
+                    toml_path: Some(report::toml_path(toml_path)),
+                    key: key.to_string(),
+                    old: old.to_string(),
+                    new: newver.to_string(),
+                },
+                format!("Version for {} was {old} want {newver}", source.as_ref()),
+            );
+            *v = opts.value_style.get().string(v, newver);
             return true;
         }
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check or update the dependencies of a manifest on member `b`
+    fn deps(args: &str, settings: &str, manifest: &str) -> (bool, String) {
+        let opts =
+            Args::try_parse_from(format!("cargo-workspace-version x {args}").split(' ')).unwrap();
+        let config: Document = format!("[workspace.metadata.{}]\n{settings}", config::TABLE)
+            .parse()
+            .unwrap();
+        let config = Config::from_document(&config).unwrap();
+        let mut doc: Document = manifest.parse().unwrap();
+        let deps = doc["dependencies"].as_table_like_mut().unwrap();
+        let members = HashSet::from(["b".to_string()]);
+        let path = Path::new("Cargo.toml");
+        let changed = update_deps(
+            path,
+            &["dependencies"],
+            deps,
+            &members,
+            &config,
+            "1.0.0",
+            &opts,
+        );
+        (changed, doc.to_string())
+    }
+
+    #[test]
+    fn compatible_requirement_left_alone() {
+        let manifest = "[dependencies]\nb = { path = \"../b\", version = \"1.0\" }\n";
+        assert_eq!(
+            deps("check 1.0.0", "", manifest),
+            (false, manifest.to_string())
+        );
+        let (changed, _) = deps("check 1.0.0 --exact-requirements", "", manifest);
+        assert!(changed);
+    }
+
+    #[test]
+    fn check_enforces_requirement_style() {
+        let manifest = "[dependencies]\nb = { path = \"../b\", version = \"1.0.0\" }\n";
+        let (changed, doc) = deps("check 1.0.0", "requirement-style = \"exact\"", manifest);
+        assert!(changed);
+        assert!(doc.contains("version = \"=1.0.0\""), "{doc}");
+        let styles = "dependency-styles = { b = \"tilde\" }";
+        let (changed, doc) = deps("update 1.0.0", styles, "[dependencies]\nb = \"=1.0.0\"\n");
+        assert!(changed);
+        assert!(doc.contains("b = \"~1.0.0\""), "{doc}");
+        let manifest = "[dependencies]\nb = \"=1.0.0\"\n";
+        let exact = "requirement-style = \"exact\"";
+        assert_eq!(
+            deps("check 1.0.0", exact, manifest),
+            (false, manifest.to_string())
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! A compact Markdown summary of `check` or `update`, to paste or post
+//! into a pull request
+use std::fmt::Write;
+
+use anyhow::Error;
+
+use crate::{
+    report::{self, Difference},
+    workspace::Workspace,
+};
+
+/// Hidden in comments we post, so later runs can find and update them
+pub const COMMENT_MARKER: &str = "<!-- cargo-workspace-version -->";
+
+/// Render the results as Markdown, with a table of crates and the
+/// changes to each file folded away
+///
+/// * `ws` - the workspace, as it was left by the update
+/// * `newver` - the version that was checked for or applied
+/// * `writing` - whether the values were changed, or only checked
+/// * `differences` - everything that didn't have the version
+/// * `error` - why the run failed, if it did for some other reason
+pub fn render(
+    ws: &Workspace,
+    newver: &str,
+    writing: bool,
+    differences: &[Difference],
+    error: Option<&Error>,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "### {}\n", report::title(newver, writing));
+    let _ = writeln!(out, "{}\n", report::summary(newver, writing, differences));
+    if let Some(e) = error.filter(|e| !e.is::<report::DifferencesFound>()) {
+        let _ = writeln!(out, "**Error:** {}\n", cell(&format!("{e:#}")));
+    }
+
+    out.push_str("| Crate | Version | Differences |\n| --- | --- | --- |\n");
+    for c in report::crates(ws, differences) {
+        let new = c.new.unwrap_or("none");
+        let version = match &c.old {
+            Some(old) => format!("{old} \u{2192} {new}"),
+            None => new.to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} |",
+            cell(c.name),
+            cell(&version),
+            c.differences
+        );
+    }
+
+    for path in report::files(differences) {
+        let in_file = differences
+            .iter()
+            .filter(|d| d.change.path == path)
+            .collect::<Vec<_>>();
+        let _ = writeln!(
+            out,
+            "\n<details>\n<summary>{} ({})</summary>\n\n```diff",
+            report::display_path(path),
+            in_file.len()
+        );
+        for d in in_file {
+            let what = d.change.toml_path.as_deref().unwrap_or(&d.change.key);
+            let _ = writeln!(
+                out,
+                "@@ line {} {what} @@\n-{}\n+{}",
+                d.line, d.change.old, d.change.new
+            );
+        }
+        out.push_str("```\n\n</details>\n");
+    }
+    out
+}
+
+/// Make text safe to put in a table cell, which ends at a `|` or a line
+/// break
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,78 @@
+//! Finding the members with `cargo metadata`, so they are exactly the
+//! ones cargo sees, however `workspace.members` is written
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::json::Json;
+
+/// The directory of each member, relative to the top of the workspace,
+/// in the order cargo lists them
+///
+/// The manifests are still read and rewritten by us; cargo only says
+/// which ones they are
+///
+/// * `default_only` - only the members in `workspace.default-members`
+pub fn members(default_only: bool) -> Result<Vec<String>, Error> {
+    // cargo tells subcommands which cargo ran them
+    let cargo = std::env::var_os("CARGO").unwrap_or("cargo".into());
+    let output = Command::new(cargo)
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .output()
+        .context("Can't run cargo metadata")?;
+    if !output.status.success() {
+        bail!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    let metadata = Json::parse(&String::from_utf8_lossy(&output.stdout))
+        .context("Can't read the output of cargo metadata")?;
+    let root = metadata
+        .get("workspace_root")
+        .and_then(Json::as_str)
+        .map(PathBuf::from)
+        .ok_or(anyhow!("cargo metadata didn't give the workspace root"))?;
+    let key = if default_only {
+        "workspace_default_members"
+    } else {
+        "workspace_members"
+    };
+    let ids = metadata
+        .get(key)
+        .and_then(Json::as_array)
+        .ok_or(anyhow!("cargo metadata didn't give {key}"))?;
+    let packages = metadata
+        .get("packages")
+        .and_then(Json::as_array)
+        .unwrap_or_default();
+    ids.iter()
+        .map(|id| {
+            let manifest = packages
+                .iter()
+                .find(|p| p.get("id").and_then(Json::as_str) == id.as_str())
+                .and_then(|p| p.get("manifest_path"))
+                .and_then(Json::as_str)
+                .ok_or(anyhow!("cargo metadata has no package for {id}"))?;
+            Ok(member_dir(&root, Path::new(manifest)))
+        })
+        .collect()
+}
+
+/// The directory of a member as `workspace.members` would list it
+fn member_dir(root: &Path, manifest: &Path) -> String {
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let dir = dir.strip_prefix(root).unwrap_or(dir);
+    if dir.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        // members are written with / on every platform
+        dir.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
@@ -0,0 +1,112 @@
+//! Telling other services about a new version by posting to a webhook
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::{
+    config::{Config, WebhookStyle},
+    git, http,
+    json::Json,
+};
+
+/// Post to the webhook in the configuration, if there is one
+///
+/// * `config` - the configuration, with the webhook and how to post to it
+/// * `old` - the version before the update, if there was one
+/// * `new` - the version everything was updated to
+/// * `crates` - the package names of the crates that were updated
+/// * `quiet` - don't say that the webhook was called
+pub fn webhook(
+    config: &Config,
+    old: Option<&str>,
+    new: &str,
+    crates: &[&str],
+    quiet: bool,
+) -> Result<(), Error> {
+    let Some(url) = &config.webhook else {
+        return Ok(());
+    };
+    // webhook URLs are secrets, so they usually come from the environment
+    let url = match url.strip_prefix('$') {
+        Some(name) => std::env::var(name)
+            .map_err(|_| anyhow!("webhook is set to ${name}, but {name} isn't set"))?,
+        None => url.clone(),
+    };
+    let tag_url = tag_url(config, new);
+    let payload = match &config.webhook_template {
+        Some(template) => {
+            // each value is escaped so it can go inside a JSON string
+            let escape = |s: &str| {
+                let quoted = Json::from(s).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            };
+            let filled = template
+                .replace("{old}", &escape(old.unwrap_or_default()))
+                .replace("{new}", &escape(new))
+                .replace("{crates}", &escape(&crates.join(", ")))
+                .replace("{tag_url}", &escape(tag_url.as_deref().unwrap_or_default()));
+            Json::parse(&filled).context("webhook-template isn't valid JSON once filled in")?
+        }
+        None => {
+            let mut text = format!("Version {new} of {}", crates.join(", "));
+            if let Some(old) = old {
+                text.push_str(&format!(" (was {old})"));
+            }
+            if let Some(tag_url) = &tag_url {
+                text.push_str(&format!("\n{tag_url}"));
+            }
+            match config.webhook_style {
+                WebhookStyle::Generic => Json::object()
+                    .with("old", old)
+                    .with("new", new)
+                    .with("crates", crates.to_vec())
+                    .with("tag_url", tag_url),
+                WebhookStyle::Slack => Json::object().with("text", text),
+                WebhookStyle::Discord => Json::object().with("content", text),
+            }
+        }
+    };
+
+    let headers = [("Content-Type", "application/json")];
+    let response = http::request("POST", &url, &headers, Some(&payload.to_string()))
+        .context("Can't call the webhook")?;
+    if !response.ok() {
+        bail!(
+            "The webhook returned {}: {}",
+            response.status,
+            response.body.trim()
+        )
+    }
+    if !quiet {
+        println!("Notified the webhook about {new}");
+    }
+    Ok(())
+}
+
+/// The web page for the release tag of a version, on the host of the
+/// `origin` remote
+///
+/// This is the GitHub form of `/releases/tag/...`, which Gitea and
+/// Forgejo also use. Remotes written as `git@host:path` or `ssh://` are
+/// turned into the https URL of the same repository
+fn tag_url(config: &Config, version: &str) -> Option<String> {
+    let remote = git::remote_url()?;
+    let base = if let Some(rest) = remote.strip_prefix("https://") {
+        // drop any credentials in the URL
+        rest.rsplit_once('@')
+            .map_or(rest, |(_, host)| host)
+            .to_string()
+    } else if let Some(rest) = remote.strip_prefix("ssh://") {
+        let rest = rest.split_once('@').map_or(rest, |(_, host)| host);
+        // a port for ssh means nothing for https
+        match rest.split_once('/') {
+            Some((host, path)) => format!("{}/{path}", host.split(':').next()?),
+            None => return None,
+        }
+    } else {
+        let (user_host, path) = remote.split_once(':')?;
+        let host = user_host.rsplit_once('@').map_or(user_host, |(_, h)| h);
+        format!("{host}/{path}")
+    };
+    let base = base.trim_end_matches('/').trim_end_matches(".git");
+    let prefix = config.tag_prefix.as_deref().unwrap_or("v");
+    Some(format!("https://{base}/releases/tag/{prefix}{version}"))
+}
@@ -0,0 +1,200 @@
+//! Rules the new version has to follow before anything is changed
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::{
+    compare,
+    config::{Config, TABLE},
+    git, ignore, registry,
+    version::{Bump, Level, Version},
+    workspace::Workspace,
+};
+
+/// The baseline that means the newest version published to crates.io
+pub const REGISTRY_BASELINE: &str = "crates.io";
+
+/// Check the new version against `minimum-version` and
+/// `denied-versions`
+///
+/// Denied versions are compared as versions when they can be, so
+/// `1.2.3` also denies `1.2.3+build`, since build metadata doesn't
+/// make a different version
+///
+/// * `config` - the settings, with the policy
+/// * `newver` - the version about to be applied
+pub fn allowed(config: &Config, newver: &str) -> Result<(), Error> {
+    if config.minimum_version.is_none() && config.denied_versions.is_empty() {
+        return Ok(());
+    }
+    let new = newver.parse::<Version>().context(format!(
+        "minimum-version and denied-versions in [workspace.metadata.{TABLE}] need a semantic version"
+    ))?;
+    if let Some(minimum) = &config.minimum_version {
+        let minimum = minimum.parse::<Version>().context(format!(
+            "minimum-version in [workspace.metadata.{TABLE}] isn't a version"
+        ))?;
+        if new < minimum {
+            bail!("{new} is below minimum-version {minimum}")
+        }
+    }
+    let key = |v: &Version| (v.major, v.minor, v.patch, v.pre.clone());
+    let same = |denied: &str| match denied.parse::<Version>() {
+        Ok(denied) => key(&denied) == key(&new),
+        Err(_) => denied == newver,
+    };
+    if let Some(denied) = config.denied_versions.iter().find(|d| same(d)) {
+        bail!("{newver} is listed in denied-versions ({denied}), so it can't be used")
+    }
+    Ok(())
+}
+
+/// Check that the new version isn't lower than the current one
+///
+/// Versions that can't be compared, because either isn't a semantic
+/// version, are let through
+///
+/// * `ws` - the workspace, for the current version
+/// * `newver` - the version about to be applied
+pub fn not_downgrade(ws: &Workspace, newver: &str) -> Result<(), Error> {
+    let Some(current) = ws.current_version() else {
+        return Ok(());
+    };
+    let (Ok(old), Ok(new)) = (current.parse::<Version>(), newver.parse::<Version>()) else {
+        return Ok(());
+    };
+    if new < old {
+        bail!("{new} is lower than the current version {old}; pass --allow-downgrade if that is intended")
+    }
+    Ok(())
+}
+
+/// Check the new version against the `branch-policies` entry for the
+/// current branch
+///
+/// The first pattern that matches the branch decides, and `*` in a
+/// pattern doesn't match `/`, so `release/*` is every release branch.
+/// Nothing is checked if no pattern matches, or there is no branch,
+/// such as when building a tag
+///
+/// * `config` - the settings, with the policies
+/// * `newver` - the version about to be applied
+pub fn branch_allowed(config: &Config, newver: &str) -> Result<(), Error> {
+    if config.branch_policies.is_empty() {
+        return Ok(());
+    }
+    let Some(branch) = git::current_branch() else {
+        return Ok(());
+    };
+    let Some((pattern, policy)) = config
+        .branch_policies
+        .iter()
+        .find(|(pattern, _)| ignore::glob_match(pattern, &branch))
+    else {
+        return Ok(());
+    };
+    let new = newver.parse::<Version>().context(format!(
+        "branch-policies in [workspace.metadata.{TABLE}] need a semantic version"
+    ))?;
+    let why = format!("branch-policies has {pattern:?} = {:?}", policy.text);
+    match policy.stable {
+        Some(true) if !new.pre.is_empty() => {
+            bail!("{new} is a pre-release, but only stable versions can be cut on {branch} ({why})")
+        }
+        Some(false) if new.pre.is_empty() => {
+            bail!("{new} is a stable version, but only pre-releases can be cut on {branch} ({why})")
+        }
+        _ => {}
+    }
+    let parts = [new.major, new.minor, new.patch];
+    let matches = policy
+        .pattern
+        .iter()
+        .zip(parts)
+        .all(|(want, part)| want.is_none_or(|want| want == part));
+    if !matches || policy.pattern.len() > parts.len() {
+        bail!("{new} isn't allowed on {branch} ({why})")
+    }
+    Ok(())
+}
+
+/// Check that the new version is at least a `level` increase over a
+/// baseline version
+///
+/// Only major, minor, and patch are compared, so with a minor
+/// requirement and a baseline of `1.2.3`, both `1.3.0` and `1.3.0-rc.1`
+/// are allowed but `1.2.4` is not
+///
+/// * `ws` - the workspace, for its members and tag prefix
+/// * `newver` - the version about to be applied
+/// * `level` - the smallest increase allowed
+/// * `baseline` - a git ref or tag, or `crates.io` for the newest
+///   version of any member published there; defaults to the latest
+///   release tag
+/// * `stable` - leave out pre-release tags and published versions
+///   when finding the baseline
+pub fn require_increase(
+    ws: &Workspace,
+    newver: &str,
+    level: Level,
+    baseline: Option<&str>,
+    stable: bool,
+) -> Result<(), Error> {
+    let Some((from, old)) = baseline_version(ws, baseline, stable)? else {
+        // a first release can be anything
+        return Ok(());
+    };
+    let new = newver
+        .parse::<Version>()
+        .context("--require-semver-increase needs a semantic version")?;
+    let required = old.bump(level);
+    let core = |v: &Version| (v.major, v.minor, v.patch);
+    if core(&new) < core(&required) {
+        let level = match level {
+            Level::Major => "major",
+            Level::Minor => "minor",
+            Level::Patch => "patch",
+        };
+        bail!(
+            "{new} is less than a {level} increase over {old} (from {from}); \
+             it has to be at least {required}"
+        )
+    }
+    Ok(())
+}
+
+/// Find the version to compare against, and say where it came from
+///
+/// Returns `None` if there is nothing to compare against, because
+/// nothing has been released yet
+fn baseline_version(
+    ws: &Workspace,
+    baseline: Option<&str>,
+    stable: bool,
+) -> Result<Option<(String, Version)>, Error> {
+    match baseline {
+        None => Ok(git::latest_release_tag(&ws.config, stable)),
+        Some(REGISTRY_BASELINE) => {
+            let mut newest: Option<Version> = None;
+            for member in ws.members.iter().filter(|m| m.publishable()) {
+                let Some(name) = member.name() else {
+                    continue;
+                };
+                let Some(version) = registry::latest_version(name, stable)? else {
+                    continue;
+                };
+                let version = version.parse::<Version>()?;
+                if newest.as_ref().is_none_or(|n| version > *n) {
+                    newest = Some(version);
+                }
+            }
+            Ok(newest.map(|v| (REGISTRY_BASELINE.to_string(), v)))
+        }
+        Some(reference) => {
+            let version = compare::load(reference)?
+                .current_version()
+                .ok_or(anyhow!("The workspace at {reference} has no version"))?
+                .parse::<Version>()
+                .context(format!("The version at {reference}"))?;
+            Ok(Some((reference.to_string(), version)))
+        }
+    }
+}
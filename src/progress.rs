@@ -0,0 +1,107 @@
+//! A progress bar for the members of a large workspace, drawn on the
+//! last line of the terminal while they are worked on
+//!
+//! There is one bar for the whole process, so anything that prints,
+//! including the logs, can move it out of the way with [`around`]
+use std::{
+    io::{IsTerminal, Write},
+    sync::Mutex,
+};
+
+/// How many characters wide the bar itself is
+const WIDTH: usize = 30;
+
+/// The bar being shown, if any
+static BAR: Mutex<Option<Progress>> = Mutex::new(None);
+
+/// The bar, and how far along it is
+#[derive(Debug)]
+struct Progress {
+    /// how many members there are
+    total: usize,
+    /// how many have been started
+    done: usize,
+    /// the one being worked on
+    name: String,
+}
+
+impl Progress {
+    /// Take the bar off the screen, before printing anything else
+    fn clear(&self) {
+        eprint!("\r\x1b[2K");
+        let _ = std::io::stderr().flush();
+    }
+
+    /// Draw the bar again, after printing something else
+    fn draw(&self) {
+        if self.total == 0 {
+            return;
+        }
+        let filled = WIDTH * self.done.min(self.total) / self.total;
+        eprint!(
+            "\r\x1b[2K[{}{}] {}/{} {}",
+            "=".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            self.done,
+            self.total,
+            self.name
+        );
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Run something with the bar, if one is shown
+fn with_bar(f: impl FnOnce(&mut Progress)) {
+    let mut bar = BAR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(bar) = bar.as_mut() {
+        f(bar);
+    }
+}
+
+/// Start showing a bar for some number of members
+///
+/// Nothing is drawn unless output goes to a terminal, so logs and
+/// pipes only get the usual output
+///
+/// * `total` - how many members there are
+/// * `wanted` - whether a bar was asked for, and we aren't quiet
+pub fn start(total: usize, wanted: bool) {
+    let enabled = wanted && std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+    *BAR.lock().unwrap_or_else(|e| e.into_inner()) = enabled.then(|| Progress {
+        total,
+        done: 0,
+        name: String::new(),
+    });
+}
+
+/// Move on to the next member, and draw the bar again
+///
+/// * `name` - the member about to be worked on
+pub fn tick(name: &str) {
+    with_bar(|bar| {
+        bar.done += 1;
+        bar.name = name.to_string();
+        bar.draw();
+    });
+}
+
+/// Take the bar off the screen for good
+pub fn finish() {
+    if let Some(bar) = BAR.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        bar.clear();
+    }
+}
+
+/// Print something, moving the bar out of the way while doing it
+///
+/// * `print` - what prints, on stdout or stderr
+pub fn around(print: impl FnOnce()) {
+    let bar = BAR.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(bar) = bar.as_ref() {
+        bar.clear();
+    }
+    print();
+    if let Some(bar) = bar.as_ref() {
+        bar.draw();
+    }
+}
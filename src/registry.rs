@@ -0,0 +1,64 @@
+//! Looking up what has been published to crates.io
+use anyhow::{anyhow, bail, Error};
+
+use crate::{http, json::Json};
+
+/// Ask crates.io about a crate
+///
+/// Returns `None` if the crate has never been published
+fn fetch(name: &str) -> Result<Option<Json>, Error> {
+    let url = format!("https://crates.io/api/v1/crates/{name}");
+    let response = http::request("GET", &url, &[], None)?;
+    if response.status == 404 {
+        return Ok(None);
+    }
+    if !response.ok() {
+        bail!("crates.io returned {} for {name}", response.status)
+    }
+    Json::parse(&response.body).map(Some)
+}
+
+/// Find the newest version of a crate published to crates.io
+///
+/// * `name` - the name of the crate
+/// * `stable` - leave out pre-releases
+///
+/// Returns `None` if the crate has never been published, or if only
+/// pre-releases have been and `stable` is set, which crates.io reports
+/// as a `null` version
+pub fn latest_version(name: &str, stable: bool) -> Result<Option<String>, Error> {
+    let Some(json) = fetch(name)? else {
+        return Ok(None);
+    };
+    let field = if stable {
+        "max_stable_version"
+    } else {
+        "max_version"
+    };
+    match json.get("crate").and_then(|c| c.get(field)) {
+        Some(Json::Null) => Ok(None),
+        Some(Json::String(version)) => Ok(Some(version.clone())),
+        _ => bail!("unexpected response from crates.io for {name}"),
+    }
+}
+
+/// Every version of a crate published to crates.io, including yanked
+/// ones, newest first
+///
+/// Returns an empty list if the crate has never been published
+pub fn versions(name: &str) -> Result<Vec<String>, Error> {
+    let Some(json) = fetch(name)? else {
+        return Ok(vec![]);
+    };
+    json.get("versions")
+        .and_then(Json::as_array)
+        .ok_or(anyhow!("unexpected response from crates.io for {name}"))?
+        .iter()
+        .map(|v| {
+            v.get("num")
+                .and_then(Json::as_str)
+                .map(String::from)
+                .ok_or(anyhow!("unexpected response from crates.io for {name}"))
+        })
+        .collect()
+}
@@ -0,0 +1,679 @@
+//! Keeping track of the differences found during a run, for reports
+//! made at the end rather than printed as we go
+use std::{
+    cell::RefCell,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Error;
+
+use crate::{
+    json::Json,
+    workspace::{DeclaredVersion, Workspace},
+};
+
+/// A value that has to change, and where it is
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// the file the value is in
+    pub path: PathBuf,
+    /// where the value is inside the file, like `package.version`, if
+    /// the file is TOML we edit as TOML
+    pub toml_path: Option<String>,
+    /// the name of the value, like `version`, a dependency, or `pkgver`
+    pub key: String,
+    /// the value that was found
+    pub old: String,
+    /// the value we want instead
+    pub new: String,
+}
+
+/// One value that didn't have what we wanted
+#[derive(Debug, Clone)]
+pub struct Difference {
+    /// what has to change
+    pub change: Change,
+    /// the line the value is on, counting from 1, as best we can tell
+    pub line: usize,
+    /// the column the old value starts at on that line, counting from 1
+    pub column: usize,
+    /// how far into the file the old value starts, in bytes
+    pub offset: usize,
+    /// the text of the line, without its line ending
+    pub text: String,
+    /// the message that was printed for it
+    pub message: String,
+}
+
+/// The error for a check that found things that don't match, as
+/// opposed to one that couldn't be carried out
+///
+/// This gets its own exit code, so CI can tell the two apart
+#[derive(Debug)]
+pub struct DifferencesFound(pub String);
+
+impl fmt::Display for DifferencesFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DifferencesFound {}
+
+/// Every difference found so far, and what else was looked at
+#[derive(Debug, Default)]
+pub struct Report {
+    /// the values that have to change
+    differences: RefCell<Vec<Difference>>,
+    /// the values that already had what we wanted
+    unchanged: RefCell<Vec<Change>>,
+    /// every file looked at, in order
+    files: RefCell<Vec<PathBuf>>,
+}
+
+impl Report {
+    /// Record a difference
+    ///
+    /// This has to be called before the file is rewritten, since the
+    /// position is found by looking for the old value in it
+    ///
+    /// * `change` - what has to change
+    /// * `message` - what was printed about it
+    pub fn add(&self, change: Change, message: String) {
+        let (line, column, offset, text) = find_position(&change);
+        self.examined(&change.path);
+        self.differences.borrow_mut().push(Difference {
+            change,
+            line,
+            column,
+            offset,
+            text,
+            message,
+        });
+    }
+
+    /// Forget everything found so far, before starting on another
+    /// workspace
+    pub fn clear(&self) {
+        self.differences.borrow_mut().clear();
+        self.unchanged.borrow_mut().clear();
+        self.files.borrow_mut().clear();
+    }
+
+    /// Record a value that already had what we wanted
+    ///
+    /// * `change` - where the value is, with the same old and new value
+    pub fn unchanged(&self, change: Change) {
+        self.examined(&change.path);
+        self.unchanged.borrow_mut().push(change);
+    }
+
+    /// Record a file that was looked at, whether or not anything in it
+    /// has to change
+    ///
+    /// * `path` - the file
+    pub fn examined(&self, path: &Path) {
+        let mut files = self.files.borrow_mut();
+        if !files.iter().any(|f| f == path) {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    /// Check if nothing has been found so far
+    pub fn is_empty(&self) -> bool {
+        self.differences.borrow().is_empty()
+    }
+
+    /// All the differences, in the order they were found
+    pub fn differences(&self) -> Vec<Difference> {
+        self.differences.borrow().clone()
+    }
+
+    /// A few lines with the totals for the whole run: the crates looked
+    /// at and changed, the requirements changed, and the files written
+    ///
+    /// * `ws` - the workspace, as it was left by the update
+    /// * `writing` - whether the values were changed, or only checked
+    pub fn totals(&self, ws: &Workspace, writing: bool) -> String {
+        let differences = self.differences.borrow();
+        let scanned = ws
+            .members
+            .iter()
+            .filter(|m| self.files.borrow().contains(&m.manifest))
+            .count();
+        let changed = crates(ws, &differences)
+            .iter()
+            .filter(|c| c.old.is_some())
+            .count();
+        let requirements = differences
+            .iter()
+            .filter(|d| {
+                d.change
+                    .toml_path
+                    .as_deref()
+                    .is_some_and(|path| path.contains("dependencies."))
+            })
+            .count();
+        let rows = if writing {
+            ["Crates changed", "Requirements changed", "Files written"]
+        } else {
+            [
+                "Crates to change",
+                "Requirements to change",
+                "Files to update",
+            ]
+        };
+        let rows = [
+            ("Crates scanned", scanned),
+            (rows[0], changed),
+            (rows[1], requirements),
+            (rows[2], files(&differences).len()),
+        ];
+        let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        rows.iter()
+            .map(|(name, count)| format!("{name:width$}  {count}\n"))
+            .collect()
+    }
+
+    /// Everything looked at as one JSON document: each file, with each
+    /// value in it that was checked and whether it changed
+    ///
+    /// * `newver` - the version that was checked for or applied
+    /// * `writing` - whether the values were changed, or only checked
+    /// * `error` - why the run failed, if it did for some other reason
+    pub fn json(&self, newver: &str, writing: bool, error: Option<&Error>) -> Json {
+        let differences = self.differences.borrow();
+        let unchanged = self.unchanged.borrow();
+        let location = |change: &Change, changed: bool| {
+            Json::object()
+                .with("toml_path", change.toml_path.clone())
+                .with("key", change.key.as_str())
+                .with("old", change.old.as_str())
+                .with("new", change.new.as_str())
+                .with("changed", changed)
+        };
+        let files = self
+            .files
+            .borrow()
+            .iter()
+            .map(|path| {
+                let mut locations = differences
+                    .iter()
+                    .filter(|d| &d.change.path == path)
+                    .map(|d| location(&d.change, true))
+                    .collect::<Vec<_>>();
+                let changed = !locations.is_empty();
+                locations.extend(
+                    unchanged
+                        .iter()
+                        .filter(|c| &c.path == path)
+                        .map(|c| location(c, false)),
+                );
+                Json::object()
+                    .with("file", display_path(path))
+                    .with("changed", changed)
+                    .with("locations", locations)
+            })
+            .collect::<Vec<_>>();
+        Json::object()
+            .with("version", newver)
+            .with("written", writing)
+            .with("files", files)
+            .with(
+                "error",
+                error
+                    .filter(|e| !e.is::<DifferencesFound>())
+                    .map(|e| format!("{e:#}")),
+            )
+    }
+
+    /// The changes as one line each for scripts, with the file, where
+    /// the value is, and its old and new value, separated by tabs
+    ///
+    /// The location is the TOML path, or the name of the value in other
+    /// files. Tabs, newlines and backslashes in any of them are escaped
+    /// as `\t`, `\n` and `\\`, so every line has four fields
+    pub fn porcelain(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+        };
+        self.differences
+            .borrow()
+            .iter()
+            .map(|d| {
+                let location = d.change.toml_path.as_deref().unwrap_or(&d.change.key);
+                format!(
+                    "{}\t{}\t{}\t{}\n",
+                    escape(&display_path(&d.change.path)),
+                    escape(location),
+                    escape(&d.change.old),
+                    escape(&d.change.new)
+                )
+            })
+            .collect()
+    }
+
+    /// The changes as a list of `{file, toml_path, key, old, new}`
+    /// objects, for tools that want to apply or audit them
+    pub fn changes(&self) -> Json {
+        let changes = self
+            .differences
+            .borrow()
+            .iter()
+            .map(|d| {
+                Json::object()
+                    .with("file", display_path(&d.change.path))
+                    .with("toml_path", d.change.toml_path.clone())
+                    .with("key", d.change.key.as_str())
+                    .with("old", d.change.old.as_str())
+                    .with("new", d.change.new.as_str())
+            })
+            .collect::<Vec<_>>();
+        Json::from(changes)
+    }
+}
+
+/// One member, as reports show it
+#[derive(Debug)]
+pub struct CrateSummary<'a> {
+    /// the package name
+    pub name: &'a str,
+    /// the version it had, if that was changed
+    pub old: Option<String>,
+    /// the version it has now, or would have once updated
+    pub new: Option<&'a str>,
+    /// how many differences were found in its manifest
+    pub differences: usize,
+}
+
+/// Summarize each member of the workspace after an update or check
+///
+/// The workspace has the new versions by then, so the old ones come
+/// from the differences, either in the member's own `[package]` or in
+/// `[workspace.package]` for a member that inherits its version
+///
+/// * `ws` - the workspace, as it was left by the update
+/// * `differences` - everything that was found
+pub fn crates<'a>(ws: &'a Workspace, differences: &[Difference]) -> Vec<CrateSummary<'a>> {
+    let old = |path: &Path, toml_path: &str| {
+        differences
+            .iter()
+            .find(|d| d.change.path == path && d.change.toml_path.as_deref() == Some(toml_path))
+            .map(|d| d.change.old.clone())
+    };
+    ws.members
+        .iter()
+        .filter_map(|m| {
+            let old = match m.declared_version() {
+                DeclaredVersion::Inherited => old(&ws.manifest, "workspace.package.version"),
+                _ => old(&m.manifest, "package.version"),
+            };
+            Some(CrateSummary {
+                name: m.name()?,
+                old,
+                new: ws.member_version(m),
+                differences: differences
+                    .iter()
+                    .filter(|d| d.change.path == m.manifest)
+                    .count(),
+            })
+        })
+        .collect()
+}
+
+/// The heading for a report of a check or update
+///
+/// * `newver` - the version that was checked for or applied
+/// * `writing` - whether the values were changed, or only checked
+pub fn title(newver: &str, writing: bool) -> String {
+    if writing {
+        format!("Version update to {newver}")
+    } else {
+        format!("Version check for {newver}")
+    }
+}
+
+/// One sentence saying how a check or update went
+///
+/// * `newver` - the version that was checked for or applied
+/// * `writing` - whether the values were changed, or only checked
+/// * `differences` - everything that didn't have the version
+pub fn summary(newver: &str, writing: bool, differences: &[Difference]) -> String {
+    let files = files(differences).len();
+    match (differences.len(), writing) {
+        (0, _) => format!("Everything has version {newver}"),
+        (n, true) => format!("{n} value(s) in {files} file(s) were updated"),
+        (n, false) => format!("{n} value(s) in {files} file(s) don't have version {newver}"),
+    }
+}
+
+/// The files the differences are in, in the order they were found
+pub fn files(differences: &[Difference]) -> Vec<&Path> {
+    let mut files = vec![];
+    for d in differences {
+        if !files.contains(&d.change.path.as_path()) {
+            files.push(d.change.path.as_path());
+        }
+    }
+    files
+}
+
+/// A path with `/` between the parts on every platform, as tools and
+/// web services expect
+pub fn display_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Join keys into a dotted TOML path, quoting any that aren't bare
+///
+/// `["target", "cfg(unix)", "dependencies"]` becomes
+/// `target."cfg(unix)".dependencies`
+pub fn toml_path<S: AsRef<str>>(keys: &[S]) -> String {
+    keys.iter()
+        .map(|key| {
+            let key = key.as_ref();
+            let bare = !key.is_empty()
+                && key
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+            if bare {
+                key.to_string()
+            } else {
+                Json::from(key).to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Find where a value is: its line and column, its byte offset, and
+/// the text of the line
+///
+/// We don't keep track of positions while editing, so the file is
+/// read again, which is why this has to happen before it is rewritten
+fn find_position(change: &Change) -> (usize, usize, usize, String) {
+    let Ok(contents) = std::fs::read_to_string(&change.path) else {
+        return (1, 1, 0, String::new());
+    };
+    position_in(
+        &contents,
+        change.toml_path.as_deref(),
+        &change.key,
+        &change.old,
+    )
+}
+
+/// Find where a value is in the contents of a file
+///
+/// A value in TOML is found from its path, by following the table
+/// headers and the keys, dotted or in an inline table. For other files,
+/// or if that fails, it is the first line with both the name and the
+/// old value, or failing that the first with just the old value, or
+/// failing that the start of the file. On a line with the name, the
+/// value is looked for after it
+///
+/// * `contents` - the file
+/// * `toml_path` - the keys leading to the value, if the file is TOML
+/// * `what` - the name of the value
+/// * `old` - the value itself
+fn position_in(
+    contents: &str,
+    toml_path: Option<&str>,
+    what: &str,
+    old: &str,
+) -> (usize, usize, usize, String) {
+    // each line with the offset it starts at
+    let lines = || {
+        contents.split_inclusive('\n').scan(0, |start, line| {
+            let at = *start;
+            *start += line.len();
+            Some((at, line.trim_end_matches(['\r', '\n'])))
+        })
+    };
+    let value_in = |line: &str, after: &str| {
+        let from = line.find(after).map_or(0, |i| i + after.len());
+        line[from..].find(old).map(|i| from + i)
+    };
+    let found = toml_path
+        .and_then(|path| match parse_keys(path) {
+            Some((keys, "")) => Some(keys),
+            _ => None,
+        })
+        .and_then(|keys| {
+            lines()
+                .enumerate()
+                .zip(toml_positions(lines().map(|(_, line)| line), &keys, old))
+                .find_map(|((i, (at, line)), column)| column.map(|column| (i, at, line, column)))
+        })
+        .or_else(|| {
+            lines().enumerate().find_map(|(i, (at, line))| {
+                line.contains(what)
+                    .then(|| value_in(line, what))
+                    .flatten()
+                    .map(|column| (i, at, line, column))
+            })
+        })
+        .or_else(|| {
+            lines()
+                .enumerate()
+                .find_map(|(i, (at, line))| value_in(line, "").map(|column| (i, at, line, column)))
+        });
+    match found {
+        Some((i, at, line, column)) => (
+            i + 1,
+            line[..column].chars().count() + 1,
+            at + column,
+            line.to_string(),
+        ),
+        None => (
+            1,
+            1,
+            0,
+            lines().next().map_or(String::new(), |(_, l)| l.to_string()),
+        ),
+    }
+}
+
+/// For each line of a TOML file, where on it the value at some path
+/// starts, if it is on that line
+///
+/// * `lines` - the lines of the file
+/// * `target` - the keys leading to the value
+/// * `old` - the value, which is looked for after its key
+fn toml_positions<'a>(
+    lines: impl Iterator<Item = &'a str> + 'a,
+    target: &'a [String],
+    old: &'a str,
+) -> impl Iterator<Item = Option<usize>> + 'a {
+    // the keys of the table header we are under, and the delimiter of
+    // a multi-line string we are inside, if any
+    let mut table = vec![];
+    let mut in_string: Option<&str> = None;
+    lines.map(move |line| {
+        if let Some(delim) = in_string {
+            if line.matches(delim).count() % 2 == 1 {
+                in_string = None;
+            }
+            return None;
+        }
+        let trimmed = line.trim_start_matches('\u{feff}').trim_start();
+        if let Some(header) = trimmed.strip_prefix('[') {
+            let header = header.strip_prefix('[').unwrap_or(header);
+            if let Some((keys, rest)) = parse_keys(header) {
+                if rest.trim_start().starts_with(']') {
+                    table = keys;
+                }
+            }
+            return None;
+        }
+        let (keys, rest) = parse_keys(trimmed)?;
+        let value = rest.trim_start().strip_prefix('=')?;
+        for delim in ["\"\"\"", "\'\'\'"] {
+            if value.matches(delim).count() % 2 == 1 {
+                in_string = Some(delim);
+            }
+        }
+        let full = table.iter().chain(&keys).collect::<Vec<_>>();
+        if full.len() > target.len() || !full.iter().zip(target).all(|(a, b)| *a == b) {
+            return None;
+        }
+        // the rest of the path is inside an inline table on this line
+        let mut from = line.len() - value.len();
+        for key in &target[full.len()..] {
+            from = find_key(line, from, key)?;
+        }
+        line[from..].find(old).map(|i| from + i)
+    })
+}
+
+/// Where a key in an inline table ends, if it is in the line after
+/// some offset
+fn find_key(line: &str, from: usize, key: &str) -> Option<usize> {
+    let quoted = Json::from(key).to_string();
+    [key, quoted.as_str()].iter().find_map(|key| {
+        line[from..].match_indices(key).find_map(|(i, _)| {
+            let start = from + i;
+            let end = start + key.len();
+            let before = line[..start].trim_end().chars().next_back();
+            let after = line[end..].trim_start();
+            (matches!(before, Some('{' | ',' | '.'))
+                && (after.starts_with('=') || after.starts_with('.')))
+            .then_some(end)
+        })
+    })
+}
+
+/// Read a dotted key, like `target."cfg(unix)".dependencies`, giving
+/// back the keys and whatever follows them
+fn parse_keys(text: &str) -> Option<(Vec<String>, &str)> {
+    let mut keys = vec![];
+    let mut rest = text;
+    loop {
+        rest = rest.trim_start();
+        let (key, after) = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut key = String::new();
+            let mut chars = quoted.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (i, '"') => break i + 1,
+                    (_, '\\') => match chars.next()?.1 {
+                        'n' => key.push('\n'),
+                        't' => key.push('\t'),
+                        'r' => key.push('\r'),
+                        'u' => {
+                            let hex = (0..4)
+                                .map(|_| chars.next().map(|(_, c)| c))
+                                .collect::<Option<String>>()?;
+                            key.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                        }
+                        c => key.push(c),
+                    },
+                    (_, c) => key.push(c),
+                }
+            };
+            (key, &quoted[end..])
+        } else if let Some(literal) = rest.strip_prefix('\'') {
+            let end = literal.find('\'')?;
+            (literal[..end].to_string(), &literal[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            (rest[..end].to_string(), &rest[end..])
+        };
+        keys.push(key);
+        match after.trim_start().strip_prefix('.') {
+            Some(next) => rest = next,
+            None => return Some((keys, after)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line and column a value is found at
+    fn line_column(contents: &str, toml_path: &str, key: &str, old: &str) -> (usize, usize) {
+        let (line, column, ..) = position_in(contents, Some(toml_path), key, old);
+        (line, column)
+    }
+
+    #[test]
+    fn dotted_key() {
+        let contents = "[package]
+name = \"a\"
+version = \"0.1.0\"
+
+[dependencies]
+core_v2 = \"0.1.0\"
+other = { path = \"../other\", version = \"0.1.0\" }
+my-core.version = \"0.1.0\"
+";
+        assert_eq!(
+            line_column(contents, "dependencies.my-core.version", "my-core", "0.1.0"),
+            (8, 20)
+        );
+    }
+
+    #[test]
+    fn sub_table() {
+        let contents = "[package]
+name = \"a\"
+version = \"0.1.0\"
+
+[dependencies]
+b = { path = \"../b\", version = \"0.1.0\" }
+
+[dev-dependencies]
+c = \"0.1.0\"
+
+[dependencies.x]
+path = \"../x\"
+version = \"0.1.0\"
+";
+        assert_eq!(
+            line_column(contents, "dependencies.x.version", "x", "0.1.0"),
+            (13, 12)
+        );
+        assert_eq!(
+            line_column(contents, "dependencies.b.version", "b", "0.1.0"),
+            (6, 33)
+        );
+        assert_eq!(
+            line_column(contents, "package.version", "version", "0.1.0"),
+            (3, 12)
+        );
+    }
+
+    #[test]
+    fn quoted_table() {
+        let contents = "[target.\"cfg(unix)\".dependencies]
+b = \"0.1.0\"
+";
+        assert_eq!(
+            line_column(
+                contents,
+                "target.\"cfg(unix)\".dependencies.b",
+                "b",
+                "0.1.0"
+            ),
+            (2, 6)
+        );
+    }
+
+    #[test]
+    fn not_toml() {
+        let (line, column, ..) = position_in("pkgname=x\npkgver=0.1.0\n", None, "pkgver", "0.1.0");
+        assert_eq!((line, column), (2, 8));
+    }
+}
@@ -0,0 +1,101 @@
+//! The results of `check` as SARIF, the format GitHub code scanning
+//! and other static analysis dashboards read, so each wrong version
+//! shows up as an alert on its line
+use anyhow::Error;
+
+use crate::{
+    json::Json,
+    report::{self, Difference},
+};
+
+/// The SARIF version written
+const VERSION: &str = "2.1.0";
+/// The schema for that version
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The one rule every result is for
+const RULE: &str = "workspace-version";
+
+/// Render the results as a SARIF log with one run
+///
+/// Each difference is a result at the start of the old value. When
+/// updating they are notes, since they were fixed, and otherwise
+/// errors
+///
+/// * `newver` - the version that was checked for or applied
+/// * `writing` - whether the values were changed, or only checked
+/// * `differences` - everything that didn't have the version
+/// * `error` - why the run failed, if it did for some other reason
+pub fn render(
+    newver: &str,
+    writing: bool,
+    differences: &[Difference],
+    error: Option<&Error>,
+) -> String {
+    let level = if writing { "note" } else { "error" };
+    let results = differences
+        .iter()
+        .map(|d| {
+            let region = Json::object()
+                .with("startLine", d.line)
+                .with("startColumn", d.column)
+                .with("endColumn", d.column + d.change.old.chars().count());
+            let location = Json::object().with(
+                "physicalLocation",
+                Json::object()
+                    .with(
+                        "artifactLocation",
+                        Json::object()
+                            .with("uri", report::display_path(&d.change.path))
+                            .with("uriBaseId", "%SRCROOT%"),
+                    )
+                    .with("region", region),
+            );
+            Json::object()
+                .with("ruleId", RULE)
+                .with("level", level)
+                .with("message", Json::object().with("text", d.message.as_str()))
+                .with("locations", vec![location])
+        })
+        .collect::<Vec<_>>();
+    let rule = Json::object()
+        .with("id", RULE)
+        .with("name", "WorkspaceVersion")
+        .with(
+            "shortDescription",
+            Json::object().with("text", "Every version in the workspace must match"),
+        )
+        .with(
+            "defaultConfiguration",
+            Json::object().with("level", "error"),
+        );
+    let driver = Json::object()
+        .with("name", env!("CARGO_PKG_NAME"))
+        .with("version", env!("CARGO_PKG_VERSION"))
+        .with("rules", vec![rule]);
+    // finding differences is what the tool is for, so only other
+    // errors mean it didn't run properly
+    let error = error.filter(|e| !e.is::<report::DifferencesFound>());
+    let mut invocation = Json::object().with("executionSuccessful", error.is_none());
+    if let Some(e) = error {
+        invocation = invocation.with(
+            "toolExecutionNotifications",
+            vec![Json::object()
+                .with("level", "error")
+                .with("message", Json::object().with("text", format!("{e:#}")))],
+        );
+    }
+    let run = Json::object()
+        .with("tool", Json::object().with("driver", driver))
+        .with("invocations", vec![invocation])
+        .with("results", results)
+        .with(
+            "properties",
+            Json::object().with("title", report::title(newver, writing)),
+        );
+    let log = Json::object()
+        .with("$schema", SCHEMA)
+        .with("version", VERSION)
+        .with("runs", vec![run]);
+    format!("{log:#}\n")
+}
@@ -0,0 +1,157 @@
+//! The `status` subcommand: everything a release manager checks by hand
+use std::path::Path;
+
+use anyhow::Error;
+
+use crate::{git, json::Json, registry, workspace::Workspace, Format};
+
+/// Where we look for the changelog
+pub const CHANGELOG: &str = "CHANGELOG.md";
+
+/// The state of the changelog
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Changelog {
+    /// there isn't one
+    Missing,
+    /// there is one, but nothing is waiting to be released
+    NoUnreleased,
+    /// there is one with an Unreleased section
+    Unreleased,
+}
+
+/// What crates.io says about one member
+#[derive(Debug)]
+enum Published {
+    /// the newest published version
+    Version(String),
+    /// never been published, or with `stable` only as pre-releases
+    Never,
+    /// `publish = false`, so we didn't ask
+    Private,
+    /// we didn't ask because of `--offline`
+    Skipped,
+    /// we couldn't find out
+    Unknown(String),
+}
+
+/// Print the workspace version, the latest tag, what each crate has
+/// published, and the state of the changelog
+///
+/// * `ws` - the workspace to report on
+/// * `offline` - don't ask crates.io about published versions
+/// * `stable` - leave out pre-release tags and published versions
+/// * `format` - text for people, or JSON for tooling
+pub fn print(ws: &Workspace, offline: bool, stable: bool, format: Format) -> Result<(), Error> {
+    let version = ws.current_version();
+    let tag = if stable {
+        git::latest_release_tag(&ws.config, true).map(|(tag, _)| tag)
+    } else {
+        git::latest_tag()
+    };
+    let changelog = changelog(Path::new(CHANGELOG));
+    let published = ws
+        .members
+        .iter()
+        .map(|m| {
+            let name = m.name().unwrap_or(&m.dir);
+            let published = if !m.publishable() {
+                Published::Private
+            } else if offline {
+                Published::Skipped
+            } else {
+                match registry::latest_version(name, stable) {
+                    Ok(Some(v)) => Published::Version(v),
+                    Ok(None) => Published::Never,
+                    Err(e) => Published::Unknown(e.to_string()),
+                }
+            };
+            (name, published)
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        Format::Text => {
+            let tag_note = match (&tag, version) {
+                (Some(tag), Some(version)) if ws.config.tag_version(tag) == Some(version) => {
+                    " (matches)"
+                }
+                (Some(_), _) => " (doesn't match)",
+                _ => "",
+            };
+            println!("Workspace version: {}", version.unwrap_or("unknown"));
+            println!(
+                "Latest git tag:    {}{tag_note}",
+                tag.as_deref().unwrap_or("none")
+            );
+            println!(
+                "Changelog:         {}",
+                match changelog {
+                    Changelog::Missing => format!("no {CHANGELOG}"),
+                    Changelog::NoUnreleased => "no Unreleased section".to_string(),
+                    Changelog::Unreleased => "has an Unreleased section".to_string(),
+                }
+            );
+            println!("Published versions:");
+            let width = published.iter().map(|(n, _)| n.len()).max().unwrap_or(0);
+            for (name, published) in &published {
+                let text = match published {
+                    Published::Version(v) => v.clone(),
+                    Published::Never if stable => "no stable release published".to_string(),
+                    Published::Never => "never published".to_string(),
+                    Published::Private => "not publishable".to_string(),
+                    Published::Skipped => "not checked (offline)".to_string(),
+                    Published::Unknown(e) => format!("unknown ({e})"),
+                };
+                println!("  {name:<width$}  {text}");
+            }
+        }
+        Format::Json => {
+            let crates = published
+                .iter()
+                .map(|(name, published)| {
+                    let (version, state) = match published {
+                        Published::Version(v) => (Some(v.as_str()), "published"),
+                        Published::Never => (None, "never-published"),
+                        Published::Private => (None, "private"),
+                        Published::Skipped => (None, "skipped"),
+                        Published::Unknown(_) => (None, "unknown"),
+                    };
+                    Json::object()
+                        .with("name", *name)
+                        .with("published", version)
+                        .with("state", state)
+                })
+                .collect::<Vec<_>>();
+            let json = Json::object()
+                .with("version", version)
+                .with("latest_tag", tag)
+                .with("changelog_exists", changelog != Changelog::Missing)
+                .with("unreleased", changelog == Changelog::Unreleased)
+                .with("crates", crates);
+            println!("{json:#}");
+        }
+    }
+    Ok(())
+}
+
+/// Look for an Unreleased heading in the changelog, as written by
+/// keep-a-changelog (`## [Unreleased]`) or without the brackets
+fn changelog(path: &Path) -> Changelog {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Changelog::Missing;
+    };
+    let unreleased = contents.lines().any(|line| {
+        line.starts_with('#')
+            && line
+                .trim_start_matches('#')
+                .trim()
+                .trim_start_matches('[')
+                .to_ascii_lowercase()
+                .starts_with("unreleased")
+    });
+    if unreleased {
+        Changelog::Unreleased
+    } else {
+        Changelog::NoUnreleased
+    }
+}
@@ -0,0 +1,415 @@
+//! Working out the version to apply from the command line argument
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Error};
+
+use crate::{
+    calver, git,
+    status::CHANGELOG,
+    version::{BuildMetadata, Bump, Level, Prerelease, Scheme, Version},
+    workspace::{DeclaredVersion, Workspace},
+};
+
+/// Turn the version argument into the version to apply
+///
+/// The argument is usually just a version, with or without a leading
+/// 'v' so that a git tag can be used. It can also be computed from the
+/// current workspace version, either with an alias (`next-patch`,
+/// `next-minor` or `next-major`, or `next` for calendar versions) or,
+/// if it contains `{`, an expression such as `{major}.{minor+1}.0`
+///
+/// * `arg` - the version argument from the command line
+/// * `ws` - the workspace, for the current version
+/// * `scheme` - how versions are numbered
+///
+/// Returns the version without any leading 'v'
+pub fn resolve(arg: &str, ws: &Workspace, scheme: Scheme) -> Result<String, Error> {
+    if arg == "next" {
+        return match scheme {
+            Scheme::Calver => calver::next(ws.current_version()),
+            Scheme::Semver => bail!(
+                "next is for --scheme calver; use next-patch, next-minor, or next-major instead"
+            ),
+        };
+    }
+    let alias = match arg {
+        "next-major" => Some(Level::Major),
+        "next-minor" => Some(Level::Minor),
+        "next-patch" => Some(Level::Patch),
+        _ => None,
+    };
+    if alias.is_none() && !arg.contains('{') {
+        return Ok(arg.strip_prefix('v').unwrap_or(arg).to_string());
+    }
+
+    let current = ws.current_version().ok_or(anyhow!(
+        "Can't compute {arg:?}: the workspace has no version"
+    ))?;
+    let current = current
+        .parse::<Version>()
+        .context(format!("Can't compute {arg:?}"))?;
+    if let Some(level) = alias {
+        return Ok(current.bump(level).to_string());
+    }
+    let version = evaluate(arg, &current)?;
+    Ok(version.strip_prefix('v').unwrap_or(&version).to_string())
+}
+
+/// The next version after the current workspace version, at a level
+///
+/// * `ws` - the workspace, for the current version
+/// * `level` - which part of the version to increment
+pub fn next(ws: &Workspace, level: Level) -> Result<String, Error> {
+    Ok(ws
+        .current_version()
+        .ok_or(anyhow!("Can't bump: the workspace has no version"))?
+        .parse::<Version>()
+        .context("Can't bump")?
+        .bump(level)
+        .to_string())
+}
+
+/// The release a pre-release previews, so `1.4.0-rc.3` becomes `1.4.0`
+///
+/// * `ws` - the workspace, for the current version
+pub fn promote(ws: &Workspace) -> Result<String, Error> {
+    let current = ws
+        .current_version()
+        .ok_or(anyhow!("Can't promote: the workspace has no version"))?
+        .parse::<Version>()
+        .context("Can't promote")?;
+    if current.pre.is_empty() {
+        bail!("{current} isn't a pre-release, so there is nothing to promote")
+    }
+    Ok(Version {
+        pre: Prerelease::EMPTY,
+        build: BuildMetadata::EMPTY,
+        ..current
+    }
+    .to_string())
+}
+
+/// Work out the build metadata to add to the version, from
+/// `--metadata`
+///
+/// `git-sha` is the abbreviated hash of the checked out commit and
+/// `env:NAME` is the value of an environment variable; anything else is
+/// used as is. Characters that can't be in build metadata, like the
+/// `/` in a branch name, become `-`
+///
+/// * `source` - what was given to `--metadata`
+pub fn metadata(source: &str) -> Result<String, Error> {
+    let raw = if source == "git-sha" {
+        git::short_sha().context("Can't find the commit for --metadata git-sha")?
+    } else if let Some(name) = source.strip_prefix("env:") {
+        std::env::var(name).context(format!("Can't read {name} for --metadata"))?
+    } else {
+        source.to_string()
+    };
+    let metadata = raw
+        .trim()
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    if metadata.is_empty() {
+        bail!("--metadata {source} gave nothing to add to the version")
+    }
+    Ok(metadata)
+}
+
+/// The environment variables CI services set to the tag a pipeline is
+/// running for, in the order they are tried
+///
+/// `GITHUB_REF_NAME` is only used when `GITHUB_REF_TYPE` is `tag`,
+/// since for branches it holds the branch name
+const CI_TAG_VARS: &[&str] = &[
+    "GITHUB_REF_NAME",
+    "CI_COMMIT_TAG",
+    "CIRCLE_TAG",
+    "BUILDKITE_TAG",
+    "TRAVIS_TAG",
+    "DRONE_TAG",
+    "BITBUCKET_TAG",
+    "APPVEYOR_REPO_TAG_NAME",
+    "TAG_NAME",
+];
+
+/// Find the version from the tag a CI pipeline is running for
+///
+/// The tag is read from the variables in [`CI_TAG_VARS`], or from
+/// `BUILD_SOURCEBRANCH` on Azure Pipelines, and the `tag-prefix` (or
+/// a leading 'v') is removed from it
+///
+/// * `ws` - the workspace, for the tag prefix
+pub fn from_ci(ws: &Workspace) -> Result<String, Error> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let tag = CI_TAG_VARS
+        .iter()
+        .filter(|name| {
+            **name != "GITHUB_REF_NAME" || var("GITHUB_REF_TYPE").as_deref() == Some("tag")
+        })
+        .find_map(|name| var(name))
+        .or_else(|| {
+            var("BUILD_SOURCEBRANCH")?
+                .strip_prefix("refs/tags/")
+                .map(String::from)
+        })
+        .ok_or(anyhow!(
+            "--from-ci didn't find a tag; this doesn't look like a pipeline started by a tag \
+             (looked at {} and BUILD_SOURCEBRANCH)",
+            CI_TAG_VARS.join(", ")
+        ))?;
+    let version = ws.config.tag_version(&tag).ok_or(anyhow!(
+        "The CI tag {tag} doesn't start with the tag prefix"
+    ))?;
+    Ok(version.to_string())
+}
+
+/// Find the version from the newest release tag, for checking that the
+/// manifests agree with the tag being built
+///
+/// Release tags are those `tag-prefix` says are for the workspace, and
+/// only those reachable from HEAD count
+///
+/// * `ws` - the workspace, for the tag prefix
+/// * `level` - bump the tag's version at this level, for the release
+///   after it
+pub fn from_git(ws: &Workspace, level: Option<Level>) -> Result<String, Error> {
+    let (tag, version) = git::latest_release_tag(&ws.config, false).ok_or(anyhow!(
+        "--from-git didn't find a release tag reachable from HEAD"
+    ))?;
+    Ok(match level {
+        Some(level) => {
+            if !version.pre.is_empty() {
+                bail!("The latest tag {tag} is a pre-release, so it can't be bumped")
+            }
+            version.bump(level).to_string()
+        }
+        None => version.to_string(),
+    })
+}
+
+/// Find the version of the newest release in the changelog, so the
+/// changelog decides what is released
+///
+/// This is the first heading that starts with a version, as written by
+/// keep-a-changelog (`## [1.2.0] - 2024-06-01`) or without the
+/// brackets, so an Unreleased section above it is passed over
+pub fn from_changelog() -> Result<String, Error> {
+    let contents = std::fs::read_to_string(CHANGELOG)
+        .context(format!("Can't read {CHANGELOG} for --from-changelog"))?;
+    contents
+        .lines()
+        .filter(|line| line.starts_with('#'))
+        .find_map(|line| {
+            let word = line.trim_start_matches('#').split_whitespace().next()?;
+            let word = word.trim_start_matches('[').trim_end_matches(']');
+            let version = word.strip_prefix('v').unwrap_or(word);
+            version
+                .parse::<Version>()
+                .is_ok()
+                .then(|| version.to_string())
+        })
+        .ok_or(anyhow!(
+            "--from-changelog didn't find a heading for a release in {CHANGELOG}"
+        ))
+}
+
+/// Find the version in an environment variable
+///
+/// The value is used as if it had been given on the command line, so
+/// it can have a leading 'v' or be an alias
+///
+/// * `name` - the name of the variable
+/// * `ws` - the workspace, for the current version
+/// * `scheme` - how versions are numbered
+pub fn from_env(name: &str, ws: &Workspace, scheme: Scheme) -> Result<String, Error> {
+    let value = std::env::var(name).context(format!("Can't read {name} for --from-env"))?;
+    let value = value.trim();
+    if value.is_empty() {
+        bail!("{name} is empty, so --from-env has no version")
+    }
+    resolve(value, ws, scheme)
+}
+
+/// Find the version in a file, like the `VERSION` file some
+/// repositories keep at the top
+///
+/// The file should have only the version in it, which is used as if it
+/// had been given on the command line
+///
+/// * `path` - the file
+/// * `ws` - the workspace, for the current version
+/// * `scheme` - how versions are numbered
+pub fn from_file(path: &Path, ws: &Workspace, scheme: Scheme) -> Result<String, Error> {
+    let contents = std::fs::read_to_string(path)
+        .context(format!("Can't read {} for --from-file", path.display()))?;
+    let value = contents.trim();
+    if value.is_empty() {
+        bail!("{} is empty, so --from-file has no version", path.display())
+    }
+    if value.contains('\n') {
+        bail!(
+            "{} has more than one line; it should have only the version",
+            path.display()
+        )
+    }
+    resolve(value, ws, scheme)
+}
+
+/// Evaluate a version expression
+///
+/// Each `{...}` is replaced with a value from the current version:
+///
+/// * `{major}`, `{minor}`, `{patch}` - the numbers, which can have a
+///   constant added or subtracted, like `{minor+1}`
+/// * `{pre}`, `{build}` - the pre-release and build metadata
+/// * `{current}` - the whole current version
+/// * `{sha}` - the abbreviated hash of the checked out commit
+///
+/// Use `{{` and `}}` for literal braces
+fn evaluate(expr: &str, current: &Version) -> Result<String, Error> {
+    let mut out = String::new();
+    let mut rest = expr;
+    while let Some(pos) = rest.find(['{', '}']) {
+        out.push_str(&rest[..pos]);
+        let brace = &rest[pos..];
+        if let Some(after) = brace.strip_prefix("{{") {
+            out.push('{');
+            rest = after;
+        } else if let Some(after) = brace.strip_prefix("}}") {
+            out.push('}');
+            rest = after;
+        } else if brace.starts_with('}') {
+            bail!("Unmatched '}}' in {expr:?}")
+        } else {
+            let end = brace
+                .find('}')
+                .ok_or(anyhow!("Unmatched '{{' in {expr:?}"))?;
+            out.push_str(&placeholder(&brace[1..end], current, expr)?);
+            rest = &brace[end + 1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Compute the value of one `{...}` placeholder
+///
+/// * `inner` - the text between the braces
+/// * `current` - the current workspace version
+/// * `expr` - the whole expression, for error messages
+fn placeholder(inner: &str, current: &Version, expr: &str) -> Result<String, Error> {
+    let inner = inner.trim();
+    let (name, offset) = match inner.find(['+', '-']) {
+        Some(pos) => {
+            let offset = inner[pos + 1..]
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow!("Bad number in {{{inner}}} in {expr:?}"))?;
+            (inner[..pos].trim(), Some((&inner[pos..pos + 1], offset)))
+        }
+        None => (inner, None),
+    };
+    let number = match name {
+        "major" => current.major,
+        "minor" => current.minor,
+        "patch" => current.patch,
+        _ if offset.is_some() => bail!("Can't do arithmetic on {{{name}}} in {expr:?}"),
+        "pre" => return Ok(current.pre.to_string()),
+        "build" => return Ok(current.build.to_string()),
+        "current" => return Ok(current.to_string()),
+        "sha" => return git::short_sha(),
+        _ => bail!("Unknown placeholder {{{name}}} in {expr:?}"),
+    };
+    let number = match offset {
+        Some(("+", n)) => number.checked_add(n),
+        Some((_, n)) => number.checked_sub(n),
+        None => Some(number),
+    };
+    number
+        .map(|n| n.to_string())
+        .ok_or(anyhow!("{{{inner}}} is out of range in {expr:?}"))
+}
+
+/// Compute the next pre-release of the current workspace version
+///
+/// The counter after the identifier is one more than the highest one
+/// already used, looking at both the manifests and the git tags, so
+/// with an existing `v1.2.0-rc.2` tag, `1.2.0-rc.1` becomes
+/// `1.2.0-rc.3`. A release version starts a pre-release of the next
+/// patch version, so `1.2.3` becomes `1.2.4-rc.1`
+///
+/// * `ws` - the workspace, for the current version and member versions
+/// * `id` - the pre-release identifier, like `rc`; defaults to the one
+///   the current version already has. A different one starts at 1, so
+///   `1.2.0-beta.3` becomes `1.2.0-rc.1`, but it can't go backwards
+pub fn prerelease(ws: &Workspace, id: Option<&str>) -> Result<String, Error> {
+    let current = ws
+        .current_version()
+        .ok_or(anyhow!("Can't bump: the workspace has no version"))?
+        .parse::<Version>()
+        .context("Can't bump")?;
+    let id = match id {
+        Some(id) => id.to_string(),
+        None => current
+            .pre
+            .split('.')
+            .next()
+            .filter(|id| !id.is_empty() && id.parse::<u64>().is_err())
+            .ok_or(anyhow!(
+                "{current} isn't a pre-release; use --id to say which kind to start"
+            ))?
+            .to_string(),
+    };
+    let base = if current.pre.is_empty() {
+        current.bump(Level::Patch)
+    } else {
+        Version {
+            pre: Prerelease::EMPTY,
+            build: BuildMetadata::EMPTY,
+            ..current
+        }
+    };
+
+    // every version we know about, from the manifests and from tags
+    let known = ws
+        .members
+        .iter()
+        .filter_map(|m| match m.declared_version() {
+            DeclaredVersion::Version(v) => Some(v.to_string()),
+            _ => None,
+        })
+        .chain(ws.current_version().map(String::from))
+        .chain(
+            git::tags()
+                .into_iter()
+                .filter_map(|tag| ws.config.tag_version(&tag).map(String::from)),
+        )
+        .filter_map(|v| v.strip_prefix('v').unwrap_or(&v).parse::<Version>().ok());
+    let highest = known
+        .filter(|v| (v.major, v.minor, v.patch) == (base.major, base.minor, base.patch))
+        .filter_map(|v| {
+            let (found, counter) = v.pre.split_once('.')?;
+            (found == id).then(|| counter.parse::<u64>().ok())?
+        })
+        .max()
+        .unwrap_or(0);
+
+    let next = Version {
+        pre: Prerelease::new(&format!("{id}.{}", highest + 1))
+            .context(format!("{id} can't be a pre-release identifier"))?,
+        ..base
+    };
+    // like going from rc back to beta
+    if next < current {
+        bail!("{next} would come before the current version {current}")
+    }
+    Ok(next.to_string())
+}
@@ -0,0 +1,109 @@
+//! Logs through `tracing`: a span for each manifest, member, extra file
+//! and dependency, and events for what was found there
+//!
+//! Two layers show them. `RUST_LOG` turns on structured logs on stderr
+//! for automated runs, filtered as `tracing-subscriber`'s `EnvFilter`
+//! does, with nothing logged if it isn't set. The `debug` and `trace`
+//! events are also what `-v` and `-vv` (or `--explain`) print on
+//! stdout, as plain lines
+use std::{fmt, io};
+
+use tracing::{level_filters::LevelFilter, Event, Level, Subscriber};
+use tracing_subscriber::{
+    filter::{filter_fn, EnvFilter},
+    fmt::{
+        format::{self, FormatEvent, FormatFields},
+        FmtContext,
+    },
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    Layer,
+};
+
+use crate::{color::Paint, progress};
+
+/// Set up the logs for this run
+///
+/// * `verbose` - how many `-v` were given
+/// * `explain` - whether `--explain` was given, which shows the
+///   reasoning like `-vv` does
+/// * `quiet` - whether nothing but errors should be printed
+/// * `color` - whether the plain lines on stdout are dimmed
+pub fn init(verbose: u8, explain: bool, quiet: bool, color: bool) {
+    let structured = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .with_writer(|| Printer(false))
+        .with_filter(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::OFF.into())
+                .from_env_lossy(),
+        );
+    // only our own debug and trace events are printed with -v, since
+    // everything at info or above is printed as we go anyway
+    let plain = tracing_subscriber::fmt::layer()
+        .event_format(Plain { color })
+        .with_writer(|| Printer(true))
+        .with_filter(filter_fn(move |meta| {
+            let wanted = match *meta.level() {
+                Level::DEBUG => verbose >= 1 || explain,
+                Level::TRACE => verbose >= 2 || explain,
+                _ => false,
+            };
+            wanted && !quiet && meta.target().starts_with(env!("CARGO_CRATE_NAME"))
+        }));
+    let _ = tracing_subscriber::registry()
+        .with(structured)
+        .with(plain)
+        .try_init();
+}
+
+/// Just the message of an event, for the lines `-v` prints
+struct Plain {
+    /// whether the line is dimmed
+    color: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut message = String::new();
+        ctx.field_format()
+            .format_fields(format::Writer::new(&mut message), event)?;
+        writeln!(writer, "{}", Paint::Quiet.apply(&message, self.color))
+    }
+}
+
+/// Writes log lines on stdout (if true) or stderr, moving the progress
+/// bar out of the way
+struct Printer(bool);
+
+impl io::Write for Printer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut result = Ok(());
+        progress::around(|| {
+            result = if self.0 {
+                io::stdout().write_all(buf)
+            } else {
+                io::stderr().write_all(buf)
+            };
+        });
+        result.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.0 {
+            io::stdout().flush()
+        } else {
+            io::stderr().flush()
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! The `verify-tags` subcommand: check that every past release tag
+//! points at a commit whose manifest has the version in its name
+use anyhow::{anyhow, Error};
+
+use crate::{
+    compare, git, json::Json, report::DifferencesFound, version::Version, workspace::Workspace,
+    Format,
+};
+
+/// What we found at one tag
+#[derive(Debug)]
+enum Finding {
+    /// the manifest has the version the tag is for
+    Matches,
+    /// the manifest has some other version
+    Differs(String),
+    /// we couldn't find a version at that commit
+    Unreadable(String),
+}
+
+/// Check each release tag against the manifests in the commit it tags
+///
+/// * `ws` - the workspace, for its configuration
+/// * `stable` - leave out tags for pre-releases
+/// * `format` - text for people, or JSON for tooling
+/// * `quiet` - don't print the text report
+///
+/// Fails if any tag doesn't match its manifest; tags where no version
+/// can be found are reported but don't count
+pub fn run(ws: &Workspace, stable: bool, format: Format, quiet: bool) -> Result<(), Error> {
+    let mut releases = git::tags()
+        .into_iter()
+        .filter_map(|tag| {
+            let version = ws.config.tag_version(&tag)?.parse::<Version>().ok()?;
+            (!stable || version.pre.is_empty()).then_some((version, tag))
+        })
+        .collect::<Vec<_>>();
+    releases.sort();
+
+    let findings = releases
+        .iter()
+        .map(|(version, tag)| {
+            let finding = match version_at(tag) {
+                Ok(found) if found == version.to_string() => Finding::Matches,
+                Ok(found) => Finding::Differs(found),
+                Err(e) => Finding::Unreadable(format!("{e:#}")),
+            };
+            (tag, version, finding)
+        })
+        .collect::<Vec<_>>();
+    let mismatches = findings
+        .iter()
+        .filter(|(_, _, f)| matches!(f, Finding::Differs(_)))
+        .count();
+
+    match format {
+        Format::Text if !quiet => {
+            if findings.is_empty() {
+                println!("No release tags found");
+            }
+            let width = findings.iter().map(|(t, _, _)| t.len()).max().unwrap_or(0);
+            for (tag, _, finding) in &findings {
+                let text = match finding {
+                    Finding::Matches => "ok".to_string(),
+                    Finding::Differs(found) => format!("manifest says {found}"),
+                    Finding::Unreadable(why) => format!("unknown ({why})"),
+                };
+                println!("{tag:<width$}  {text}");
+            }
+        }
+        Format::Text => {}
+        Format::Json => {
+            let tags = findings
+                .iter()
+                .map(|(tag, version, finding)| {
+                    let (state, found, error) = match finding {
+                        Finding::Matches => ("ok", Some(version.to_string()), None),
+                        Finding::Differs(found) => ("mismatch", Some(found.clone()), None),
+                        Finding::Unreadable(why) => ("unknown", None, Some(why.as_str())),
+                    };
+                    Json::object()
+                        .with("tag", tag.as_str())
+                        .with("version", version.to_string())
+                        .with("state", state)
+                        .with("manifest_version", found)
+                        .with("error", error)
+                })
+                .collect::<Vec<_>>();
+            println!("{:#}", Json::from(tags));
+        }
+    }
+    if mismatches > 0 {
+        return Err(DifferencesFound(format!(
+            "{mismatches} tag(s) don't match the version in their manifest"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// The version of the workspace as it was at a tag
+///
+/// The workspace is read from the tag the way `compare` reads one, so
+/// its members are found with the same globs and `exclude`, and the
+/// version is the same one the current workspace would report
+///
+/// * `tag` - the tag to look at
+fn version_at(tag: &str) -> Result<String, Error> {
+    // the full name, so a directory named like the tag isn't read instead
+    compare::load(&format!("refs/tags/{tag}"))?
+        .current_version()
+        .map(String::from)
+        .ok_or(anyhow!("no version in the manifests"))
+}
@@ -0,0 +1,166 @@
+//! Semantic versions, as described at <https://semver.org>, with the
+//! parsing, ordering and requirement matching of the `semver` crate
+//! that cargo itself uses
+use anyhow::{anyhow, Error};
+use clap::ValueEnum;
+use semver::VersionReq;
+pub use semver::{BuildMetadata, Prerelease, Version};
+
+/// Which part of a version to increment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Level {
+    /// incompatible changes: 1.2.3 becomes 2.0.0
+    Major,
+    /// compatible additions: 1.2.3 becomes 1.3.0
+    Minor,
+    /// compatible fixes: 1.2.3 becomes 1.2.4
+    Patch,
+}
+
+/// How versions are numbered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Scheme {
+    /// semantic versions, like 1.2.3
+    #[default]
+    Semver,
+    /// calendar versions, like 2024.6.1: the year, the month, and a
+    /// counter for each release in the month
+    Calver,
+}
+
+/// Computing the next version
+pub trait Bump {
+    /// Compute the next version at the given level
+    ///
+    /// A pre-release is a preview of the version it is attached to, so
+    /// if that version is already the next one at this level, bumping
+    /// just drops the pre-release: 1.3.0-rc.1 bumps to 1.3.0 for either
+    /// minor or patch, but to 2.0.0 for major. Build metadata is
+    /// always dropped
+    fn bump(&self, level: Level) -> Version;
+}
+
+impl Bump for Version {
+    fn bump(&self, level: Level) -> Version {
+        let pre = !self.pre.is_empty();
+        let (major, minor, patch) = match level {
+            Level::Major if pre && self.minor == 0 && self.patch == 0 => (self.major, 0, 0),
+            Level::Major => (self.major + 1, 0, 0),
+            Level::Minor if pre && self.patch == 0 => (self.major, self.minor, 0),
+            Level::Minor => (self.major, self.minor + 1, 0),
+            Level::Patch if pre => (self.major, self.minor, self.patch),
+            Level::Patch => (self.major, self.minor, self.patch + 1),
+        };
+        Version::new(major, minor, patch)
+    }
+}
+
+/// Check if a version requirement allows a version, with the rules
+/// cargo uses
+///
+/// A pre-release is only allowed if one of the comparators is for a
+/// pre-release of the same version, and build metadata is ignored
+///
+/// * `requirement` - the requirement, like `1.2` or `>=1.0, <3`
+/// * `version` - the version to check
+pub fn satisfies(requirement: &str, version: &Version) -> Result<bool, Error> {
+    let requirement = VersionReq::parse(requirement)
+        .map_err(|e| anyhow!("{requirement:?} isn't a valid requirement: {e}"))?;
+    Ok(requirement.matches(version))
+}
+
+/// Split a tag like `mycrate-v1.2.3` into what comes before the
+/// version and the version itself
+///
+/// The version is the longest tail of the tag that parses, so
+/// anything can come before it
+pub fn split_tag(tag: &str) -> Option<(&str, Version)> {
+    tag.char_indices().find_map(|(i, _)| {
+        let version = tag[i..].parse().ok()?;
+        Some((&tag[..i], version))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(text: &str) -> Version {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn pre_release_ordering() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(v(pair[0]) < v(pair[1]), "{} < {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn build_metadata() {
+        let built = v("1.2.3+abc.5");
+        assert_eq!(built.build.as_str(), "abc.5");
+        assert_eq!(built.to_string(), "1.2.3+abc.5");
+        assert!(satisfies("=1.2.3", &built).unwrap());
+        assert!(built.bump(Level::Patch).build.is_empty());
+    }
+
+    #[test]
+    fn invalid() {
+        for text in ["1.2", "v1.2.3", "1..3", "01.2.3", "1.2.3-", "1.2.3-01"] {
+            assert!(text.parse::<Version>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn requirements() {
+        let cases = [
+            ("1.2", "1.2.0", true),
+            ("1.2", "1.9.9", true),
+            ("1.2", "2.0.0", false),
+            ("^0.2.3", "0.2.9", true),
+            ("^0.2.3", "0.3.0", false),
+            ("^0.0.3", "0.0.4", false),
+            ("~1.2.3", "1.2.9", true),
+            ("~1.2.3", "1.3.0", false),
+            ("~1", "1.9.0", true),
+            ("*", "3.1.4", true),
+            ("1.*", "1.4.0", true),
+            ("1.*", "2.0.0", false),
+            (">=1.0, <3", "2.5.0", true),
+            (">=1.0, <3", "3.0.0", false),
+            (">1.2.3", "1.2.3", false),
+            ("<=1.2", "1.2.7", true),
+            ("=1.2.3", "1.2.4", false),
+            ("1.2", "1.3.0-rc.1", false),
+            (">=1.3.0-rc.1", "1.3.0-rc.2", true),
+        ];
+        for (requirement, version, allowed) in cases {
+            assert_eq!(
+                satisfies(requirement, &v(version)).unwrap(),
+                allowed,
+                "{requirement} {version}"
+            );
+        }
+        assert!(satisfies("1.2.x.y", &v("1.2.0")).is_err());
+    }
+
+    #[test]
+    fn bump() {
+        assert_eq!(v("1.2.3").bump(Level::Minor), v("1.3.0"));
+        assert_eq!(v("1.3.0-rc.1").bump(Level::Minor), v("1.3.0"));
+        assert_eq!(v("1.3.0-rc.1").bump(Level::Major), v("2.0.0"));
+        assert_eq!(v("2.0.0-rc.1").bump(Level::Major), v("2.0.0"));
+        assert_eq!(v("1.2.3-rc.1").bump(Level::Patch), v("1.2.3"));
+    }
+}
@@ -0,0 +1,685 @@
+//! Loading the workspace: the top level manifest and the manifest
+//! of each of its members
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Context, Error};
+use toml_edit::{Document, Item, TableLike};
+
+use crate::{
+    cargo_release,
+    config::Config,
+    encoding,
+    ignore::{glob_match, Ignore},
+};
+
+/// The top level manifest and all of the members it lists
+#[derive(Debug)]
+pub struct Workspace {
+    /// the path of the top level Cargo.toml
+    pub manifest: PathBuf,
+    /// the parsed top level Cargo.toml
+    pub doc: Document,
+    /// our settings from `[workspace.metadata.workspace-version]`
+    pub config: Config,
+    /// each member, in the order they are listed
+    pub members: Vec<Member>,
+    /// why members that couldn't be read were left out of `members`
+    pub errors: Vec<Error>,
+}
+
+/// One member of the workspace
+#[derive(Debug)]
+pub struct Member {
+    /// the entry for this member in `workspace.members`
+    pub dir: String,
+    /// the path of this member's Cargo.toml
+    pub manifest: PathBuf,
+    /// the parsed Cargo.toml of this member
+    pub doc: Document,
+    /// what cargo-release is configured to do with this member
+    pub cargo_release: cargo_release::Settings,
+}
+
+/// The version a member declares in its `[package]` section
+#[derive(Debug, PartialEq)]
+pub enum DeclaredVersion<'a> {
+    /// `version = "..."`
+    Version(&'a str),
+    /// `version.workspace = true`, taken from `[workspace.package]`
+    Inherited,
+    /// No `[package]` section, no version, or something we can't read
+    Missing,
+}
+
+impl Workspace {
+    /// Read the top level Cargo.toml in the current directory and the
+    /// Cargo.toml of each member listed in it
+    ///
+    /// * `members` - the members to use instead of those listed in
+    ///   `workspace.members`, if any are given
+    /// * `default_only` - use `workspace.default-members` instead of
+    ///   `workspace.members`
+    pub fn load(members: &[String], default_only: bool) -> Result<Workspace, Error> {
+        Workspace::read_members(
+            |path| Ok(std::fs::read_to_string(path)?),
+            |dir| list_dirs(Path::new("."), dir),
+            members,
+            default_only,
+        )
+    }
+
+    /// Read the workspace from somewhere other than the current
+    /// directory, such as another commit
+    ///
+    /// * `read` - returns the contents of a file, given its path
+    ///   relative to the workspace root
+    /// * `list` - returns the names of the subdirectories of a
+    ///   directory, for expanding glob patterns in `workspace.members`
+    pub fn read<F, L>(read: F, list: L) -> Result<Workspace, Error>
+    where
+        F: Fn(&Path) -> Result<String, Error> + Sync,
+        L: Fn(&Path) -> Vec<OsString>,
+    {
+        let mut ws = Workspace::read_members(read, list, &[], false)?;
+        problems(std::mem::take(&mut ws.errors))?;
+        Ok(ws)
+    }
+
+    /// Read the workspace, with the members listed in the top level
+    /// Cargo.toml unless others are given
+    fn read_members<F, L>(
+        read: F,
+        list: L,
+        only: &[String],
+        default_only: bool,
+    ) -> Result<Workspace, Error>
+    where
+        F: Fn(&Path) -> Result<String, Error> + Sync,
+        L: Fn(&Path) -> Vec<OsString>,
+    {
+        // first read the top level Cargo.toml
+        let manifest = PathBuf::from("Cargo.toml");
+        let base = read(&manifest)?;
+        let doc = encoding::without_bom(&base).parse::<Document>()?;
+        let mut config = Config::from_document(&doc)?;
+        let release = read(Path::new("release.toml")).ok();
+        let release =
+            cargo_release::Settings::workspace(&doc, release.as_deref().map(encoding::without_bom))
+                .context("Can't read the cargo-release settings in release.toml")?;
+        let entries = if only.is_empty() {
+            // get the [workspace] section
+            let workspace = doc
+                .get("workspace")
+                .ok_or(anyhow!("No [workspace] section in top level"))?;
+            // find the members array inside the workspace
+            let key = if default_only {
+                "default-members"
+            } else {
+                "members"
+            };
+            match workspace.get(key) {
+                Some(entries) => entries
+                    .as_array()
+                    .ok_or(anyhow!("{key} must be an array"))?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, entry)| {
+                        entry.as_str().map(String::from).ok_or(anyhow!(
+                            "{key}[{i}] in [workspace] must be a string, not {}",
+                            entry.type_name()
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                // as in cargo, a root package can leave the list out, and
+                // is then the only member and the default one
+                None if doc.contains_key("package") && default_only => vec![".".to_string()],
+                None if doc.contains_key("package") => vec![],
+                None => bail!("No {key} in [workspace] section"),
+            }
+        } else {
+            // given on the command line, so the manifest's list (or
+            // lack of one) doesn't matter
+            only.to_vec()
+        };
+
+        let mut seen = HashSet::new();
+        let exclude = excluded_dirs(&doc)?;
+        let mut errors = vec![];
+        let mut dirs = entries
+            .iter()
+            .flat_map(|entry| expand_member(entry, &exclude, &list, &mut errors))
+            .collect::<Vec<_>>();
+        // as in cargo, a package at the top is a member whether or not
+        // it is listed, unless default-members leaves it out
+        if only.is_empty()
+            && !default_only
+            && doc.contains_key("package")
+            && !dirs.iter().any(|dir| normalize_member(dir) == ".")
+        {
+            dirs.insert(0, ".".to_string());
+        }
+        // the same directory listed twice would be processed twice
+        dirs.retain(|dir| seen.insert(dir.clone()));
+        // each member is read and parsed on its own, which takes a while
+        // in a large workspace, so it is done on all the cores
+        let loaded = parallel_map(&dirs, |dir| {
+            // calculate the path of the inner member
+            let manifest: PathBuf = [dir.as_str(), "Cargo.toml"].iter().collect();
+            // and load into a parsed toml document
+            let doc = read(&manifest)
+                .context(format!("Can't read {}", manifest.display()))
+                .and_then(|inner| {
+                    encoding::without_bom(&inner)
+                        .parse::<Document>()
+                        .context(format!("Can't parse {}", manifest.display()))
+                });
+            let file: PathBuf = [dir.as_str(), "release.toml"].iter().collect();
+            let loaded = doc.and_then(|doc| {
+                let cargo_release = release
+                    .package(&doc, read(&file).ok().as_deref().map(encoding::without_bom))
+                    .context(format!(
+                        "Can't read the cargo-release settings in {}",
+                        file.display()
+                    ))?;
+                Ok((doc, cargo_release))
+            });
+            loaded.map(|(doc, cargo_release)| Member {
+                dir: dir.clone(),
+                manifest,
+                doc,
+                cargo_release,
+            })
+        });
+        // the others are still worth looking at, so callers decide
+        let mut members = vec![];
+        for member in loaded {
+            match member {
+                Ok(member) => members.push(member),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // cargo-release settings fill in whatever we weren't told
+        if config.tag_prefix.is_none() {
+            config.tag_prefix = release.release_tag_prefix();
+        }
+        if config.post_update_hook.is_empty() {
+            config.post_update_hook = release.hook.clone().unwrap_or_default();
+        }
+        for member in &members {
+            if member.cargo_release.release == Some(false) {
+                if let Some(name) = member.name() {
+                    if !config.skip.iter().any(|s| s == name) {
+                        config.skip.push(name.to_string());
+                    }
+                }
+            }
+        }
+        Ok(Workspace {
+            manifest,
+            doc,
+            config,
+            members,
+            errors,
+        })
+    }
+
+    /// The set of package names of the members, for checking if a
+    /// dependency refers to one of the members of this workspace
+    pub fn members_lookup(&self) -> HashSet<String> {
+        self.members
+            .iter()
+            .filter_map(|m| m.name().map(String::from))
+            .collect()
+    }
+
+    /// The version of the workspace as a whole
+    ///
+    /// This is `[workspace.package].version` if there is one, otherwise
+    /// the version of the root package, otherwise the version declared
+    /// by the first member that has one
+    pub fn current_version(&self) -> Option<&str> {
+        self.package_version()
+            .or_else(|| {
+                self.doc
+                    .get("package")
+                    .and_then(|p| p.get("version"))
+                    .and_then(Item::as_str)
+            })
+            .or_else(|| {
+                self.members
+                    .iter()
+                    .find_map(|m| match m.declared_version() {
+                        DeclaredVersion::Version(v) => Some(v),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// The version a member has, taking inheritance from
+    /// `[workspace.package]` into account
+    pub fn member_version<'a>(&'a self, member: &'a Member) -> Option<&'a str> {
+        match member.declared_version() {
+            DeclaredVersion::Version(v) => Some(v),
+            DeclaredVersion::Inherited => self.package_version(),
+            DeclaredVersion::Missing => None,
+        }
+    }
+
+    /// The version in `[workspace.package]`, which members can inherit
+    pub fn package_version(&self) -> Option<&str> {
+        self.doc
+            .get("workspace")
+            .and_then(|w| w.get("package"))
+            .and_then(|p| p.get("version"))
+            .and_then(Item::as_str)
+    }
+}
+
+impl Member {
+    /// The package name from `[package]`, if there is one
+    pub fn name(&self) -> Option<&str> {
+        self.doc
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(Item::as_str)
+    }
+
+    /// Check if this package can be published, which it can unless
+    /// `publish = false` or `publish = []`, or cargo-release is told
+    /// not to publish it
+    pub fn publishable(&self) -> bool {
+        if self.cargo_release.publish == Some(false) {
+            return false;
+        }
+        match self.doc.get("package").and_then(|p| p.get("publish")) {
+            Some(publish) => {
+                publish.as_bool().unwrap_or(true)
+                    && publish.as_array().is_none_or(|a| !a.is_empty())
+            }
+            None => true,
+        }
+    }
+
+    /// The version from `[package]`, as it's written in the manifest
+    pub fn declared_version(&self) -> DeclaredVersion<'_> {
+        match self.doc.get("package").and_then(|p| p.get("version")) {
+            Some(version) => {
+                if let Some(version) = version.as_str() {
+                    DeclaredVersion::Version(version)
+                } else if is_inherited(version) {
+                    DeclaredVersion::Inherited
+                } else {
+                    DeclaredVersion::Missing
+                }
+            }
+            None => DeclaredVersion::Missing,
+        }
+    }
+}
+
+/// Call a function on each item on as many threads as there are
+/// cores, giving back the results in the order of the items
+///
+/// * `items` - what to call it on
+/// * `f` - the function, which must be safe to call from any thread
+fn parallel_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if threads == 1 || items.len() < 2 {
+        return items.iter().map(f).collect();
+    }
+    let size = items.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let f = &f;
+        let handles = items
+            .chunks(size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    })
+}
+
+/// Fail with every problem found, if there were any
+///
+/// One problem is returned as it is, and more are listed together
+///
+/// * `errors` - the problems, in the order they were found
+pub fn problems(mut errors: Vec<Error>) -> Result<(), Error> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        n => {
+            let list = errors
+                .iter()
+                .map(|e| format!("  {}", format!("{e:#}").trim_end().replace('\n', "\n    ")))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Err(anyhow!("There were {n} problems:\n{list}"))
+        }
+    }
+}
+
+/// Put a member entry into a canonical form
+///
+/// `./crates/a/`, `crates//a` and `crates/a` all name the same
+/// directory, so they all become `crates/a`. The workspace root
+/// itself becomes `.`
+pub fn normalize_member(entry: &str) -> String {
+    let parts = entry
+        .split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+/// Check if a member entry is a glob pattern, like `crates/*`
+pub fn is_glob(entry: &str) -> bool {
+    entry.contains(['*', '?', '['])
+}
+
+/// The member directories an entry in `workspace.members` names
+///
+/// An entry that isn't a glob pattern names one directory, whether or
+/// not it exists. A pattern names every directory that matches it,
+/// like cargo, in order by name; `**` matches any number of
+/// directories. As in cargo, directories in `workspace.exclude`, or
+/// below one, aren't matched by a pattern, but can still be listed
+/// themselves
+///
+/// * `entry` - the entry, like `crates/*`
+/// * `exclude` - the entries of `workspace.exclude`, normalized
+/// * `list` - returns the names of the subdirectories of a directory
+/// * `errors` - where directories a pattern matches are added if
+///   their names can't be used, since they aren't UTF-8
+pub fn expand_member<L>(
+    entry: &str,
+    exclude: &[String],
+    list: &L,
+    errors: &mut Vec<Error>,
+) -> Vec<String>
+where
+    L: Fn(&Path) -> Vec<OsString>,
+{
+    let entry = normalize_member(entry);
+    if !is_glob(&entry) {
+        return vec![entry];
+    }
+    let parts = entry.split('/').collect::<Vec<_>>();
+    let mut found = vec![];
+    let mut unusable = vec![];
+    expand(list, ".", &parts, &mut found, &mut unusable);
+    let excluded = |dir: &str| {
+        exclude.iter().any(|ex| {
+            ex == "."
+                || dir == ex
+                || dir
+                    .strip_prefix(ex.as_str())
+                    .is_some_and(|r| r.starts_with('/'))
+        })
+    };
+    errors.extend(
+        unusable
+            .into_iter()
+            .filter(|dir| !excluded(&dir.to_string_lossy()))
+            .map(|dir| {
+                anyhow!(
+                    "{} matches {entry:?} in members, but its name isn't UTF-8",
+                    dir.display()
+                )
+            }),
+    );
+    found.retain(|dir| !excluded(dir));
+    found
+}
+
+/// The directories in `workspace.exclude`, normalized like members
+///
+/// * `doc` - the top level manifest
+pub fn excluded_dirs(doc: &Document) -> Result<Vec<String>, Error> {
+    let Some(exclude) = doc.get("workspace").and_then(|w| w.get("exclude")) else {
+        return Ok(vec![]);
+    };
+    let exclude = exclude
+        .as_array()
+        .ok_or(anyhow!("exclude in [workspace] must be an array"))?;
+    exclude
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            entry.as_str().map(normalize_member).ok_or(anyhow!(
+                "exclude[{i}] in [workspace] must be a string, not {}",
+                entry.type_name()
+            ))
+        })
+        .collect()
+}
+
+/// The recursive part of [`expand_member`], matching `parts` below
+/// `dir`, and adding to `unusable` what matches but isn't UTF-8
+fn expand<L>(
+    list: &L,
+    dir: &str,
+    parts: &[&str],
+    found: &mut Vec<String>,
+    unusable: &mut Vec<PathBuf>,
+) where
+    L: Fn(&Path) -> Vec<OsString>,
+{
+    let join = |name: &str| {
+        if dir == "." {
+            name.to_string()
+        } else {
+            format!("{dir}/{name}")
+        }
+    };
+    let Some((part, rest)) = parts.split_first() else {
+        found.push(dir.to_string());
+        return;
+    };
+    let (subdirs, others): (Vec<_>, Vec<_>) = list(Path::new(dir))
+        .into_iter()
+        .map(OsString::into_string)
+        .partition(Result::is_ok);
+    let subdirs = subdirs
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    // a name that isn't UTF-8 can't be a member, but is worth
+    // reporting if the pattern would have matched it
+    for other in others.into_iter().filter_map(Result::err) {
+        if *part == "**" || (is_glob(part) && glob_match(part, &other.to_string_lossy())) {
+            unusable.push(if dir == "." {
+                PathBuf::from(other)
+            } else {
+                Path::new(dir).join(other)
+            });
+        }
+    }
+    if *part == "**" {
+        expand(list, dir, rest, found, unusable);
+        for subdir in subdirs {
+            expand(list, &join(&subdir), parts, found, unusable);
+        }
+    } else if is_glob(part) {
+        for subdir in subdirs.iter().filter(|d| glob_match(part, d)) {
+            expand(list, &join(subdir), rest, found, unusable);
+        }
+    } else if subdirs.iter().any(|d| d == part) {
+        expand(list, &join(part), rest, found, unusable);
+    }
+}
+
+/// The names of the subdirectories of a directory, in order
+///
+/// Returns an empty list if the directory can't be read
+///
+/// * `root` - the workspace root
+/// * `dir` - the directory, relative to the root
+pub fn list_dirs(root: &Path, dir: &Path) -> Vec<OsString> {
+    let Ok(entries) = std::fs::read_dir(root.join(dir)) else {
+        return vec![];
+    };
+    let mut dirs = entries
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.file_name())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs
+}
+
+/// The directories below the workspace that are workspaces of their
+/// own, like one under `tools/` that this one excludes, nearest first
+///
+/// Directories that are ignored, hidden, or `target` aren't looked in,
+/// as for the extra files `init` finds
+///
+/// * `root` - the top of the workspace
+pub fn nested(root: &Path) -> Vec<PathBuf> {
+    let mut found = vec![];
+    find_nested(root, &Ignore::root(root), &mut found);
+    found
+        .iter()
+        .map(|dir| dir.strip_prefix(root).unwrap_or(dir).to_path_buf())
+        .collect()
+}
+
+/// Look for workspaces in a directory and the ones below it
+fn find_nested(dir: &Path, ignore: &Ignore, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut dirs = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|path| path.is_dir() && !ignore.is_ignored(path, true))
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| !name.starts_with('.') && name != "target")
+        })
+        .collect::<Vec<_>>();
+    dirs.sort();
+    for path in dirs {
+        if read_manifest(&path).is_some_and(|doc| doc.contains_key("workspace")) {
+            found.push(path.clone());
+        }
+        find_nested(&path, &ignore.enter(&path), found);
+    }
+}
+
+/// The top of the workspace the current directory is in, if it isn't
+/// the top itself, found as cargo does
+///
+/// That is the directory a member's `package.workspace` points to, or
+/// else the nearest one above with a `[workspace]`
+pub fn find_root() -> Option<PathBuf> {
+    let here = std::env::current_dir().ok()?;
+    let doc = read_manifest(&here);
+    if doc
+        .as_ref()
+        .is_some_and(|doc| doc.contains_key("workspace"))
+    {
+        return None;
+    }
+    if let Some(dir) = doc
+        .as_ref()
+        .and_then(|doc| doc.get("package"))
+        .and_then(|p| p.get("workspace"))
+        .and_then(Item::as_str)
+    {
+        return Some(here.join(dir));
+    }
+    here.ancestors()
+        .skip(1)
+        .find(|dir| read_manifest(dir).is_some_and(|doc| doc.contains_key("workspace")))
+        .map(PathBuf::from)
+}
+
+/// The Cargo.toml in a directory, if there is one we can read
+fn read_manifest(dir: &Path) -> Option<Document> {
+    let contents = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    encoding::without_bom(&contents).parse().ok()
+}
+
+/// The name of the package a dependency refers to
+///
+/// This is the `package` key if the dependency was renamed, or else
+/// the name of the dependency itself
+///
+/// * `key` - the name the dependency is listed under
+/// * `dep` - the value describing the dependency
+pub fn dependency_package<'a>(key: &'a str, dep: &'a Item) -> &'a str {
+    dep.get("package").and_then(Item::as_str).unwrap_or(key)
+}
+
+/// Check if a value is `{ workspace = true }`, in any of its spellings
+pub fn is_inherited(item: &Item) -> bool {
+    item.get("workspace")
+        .and_then(Item::as_bool)
+        .unwrap_or_default()
+}
+
+/// The names of the tables dependencies can be listed in
+pub const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Call `f` on every table of dependencies in a manifest
+///
+/// These are the normal, dev and build dependency tables, the same
+/// tables for each `[target.'...']`, and `[workspace.dependencies]`.
+/// `f` is given the keys leading to the table, such as
+/// `["target", "cfg(windows)", "dependencies"]`. Each table can be
+/// written in any TOML spelling: a `[header]`, an inline table, or
+/// dotted keys like `target."cfg(unix)".dependencies.foo.version`
+pub fn visit_dependency_tables<F>(doc: &mut Document, mut f: F)
+where
+    F: FnMut(&[&str], &mut dyn TableLike),
+{
+    for kind in DEPENDENCY_TABLES {
+        if let Some(table) = doc.get_mut(kind).and_then(Item::as_table_like_mut) {
+            f(&[kind], table);
+        }
+    }
+    if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+        for (target, item) in targets.iter_mut() {
+            for kind in DEPENDENCY_TABLES {
+                // looked up through the table, since Item::get_mut would
+                // add the key when it isn't there
+                let table = item
+                    .as_table_like_mut()
+                    .and_then(|t| t.get_mut(kind))
+                    .and_then(Item::as_table_like_mut);
+                if let Some(table) = table {
+                    f(&["target", target.get(), kind], table);
+                }
+            }
+        }
+    }
+    if let Some(table) = doc
+        .get_mut("workspace")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|w| w.get_mut("dependencies"))
+        .and_then(Item::as_table_like_mut)
+    {
+        f(&["workspace", "dependencies"], table);
+    }
+}